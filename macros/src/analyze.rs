@@ -1,49 +1,778 @@
 use syn::spanned::Spanned;
 
-use crate::ast;
+use crate::ast::{self, Value as _};
+use crate::html;
+use crate::options::Options;
 
-pub(crate) fn analyze_nodes<V: Spanned>(
+pub(crate) fn analyze_nodes<V: ast::Value + Spanned>(
     nodes: &[ast::Node<V>],
+    options: &Options,
 ) -> syn::Result<()> {
-    check_node_tree(nodes)?;
-    check_duplicate_attrs(nodes)?;
-    Ok(())
+    let mut errors = Errors::default();
+    let well_formed = errors.check(check_node_tree(nodes)).is_some();
+    errors.check(check_duplicate_attrs(nodes));
+    errors.check(check_components(nodes));
+    errors.check(check_blocks(nodes));
+    // The nesting of elements is only meaningful for a well-formed tree.
+    if options.strict && well_formed {
+        errors.check(check_content_model(nodes, &[]));
+    }
+    if options.lint && well_formed {
+        errors.check(check_names(nodes, &[]));
+    }
+    if options.a11y && well_formed {
+        errors.check(check_a11y(nodes));
+    }
+    errors.finish()
+}
+
+/// Analyzes the nodes of a template with formatting arguments, after its
+/// layout is resolved.
+pub(crate) fn analyze_template(
+    nodes: &[ast::Node<ast::ArgValue>],
+    options: &Options,
+) -> syn::Result<()> {
+    let mut errors = Errors::default();
+    errors.check(analyze_nodes(nodes, options));
+    errors.check(check_event_handlers(nodes));
+    errors.finish()
+}
+
+/// Accumulates errors, so that every problem in a template is reported at
+/// once instead of one per compilation.
+#[derive(Default)]
+pub(crate) struct Errors(Option<syn::Error>);
+
+impl Errors {
+    pub(crate) fn push(&mut self, error: syn::Error) {
+        match &mut self.0 {
+            Some(errors) => errors.combine(error),
+            None => self.0 = Some(error),
+        }
+    }
+
+    /// Pushes the error of `result`, if any, and returns its value otherwise.
+    pub(crate) fn check<T>(&mut self, result: syn::Result<T>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(error) => {
+                self.push(error);
+                None
+            }
+        }
+    }
+
+    pub(crate) fn finish(self) -> syn::Result<()> {
+        self.0.map_or(Ok(()), Err)
+    }
+}
+
+/// Calls `f` for every tag, including tags in nested nodes.
+fn for_each_tag<V>(
+    nodes: &[ast::Node<V>],
+    f: &mut impl FnMut(&ast::Tag<V>, &mut Errors),
+) -> syn::Result<()> {
+    fn visit<V>(
+        nodes: &[ast::Node<V>],
+        f: &mut impl FnMut(&ast::Tag<V>, &mut Errors),
+        errors: &mut Errors,
+    ) {
+        for node in nodes {
+            if let ast::Node::Tag(tag) = node {
+                f(tag, errors);
+            }
+            for branch in node.branches() {
+                visit(branch, f, errors);
+            }
+        }
+    }
+
+    let mut errors = Errors::default();
+    visit(nodes, f, &mut errors);
+    errors.finish()
+}
+
+/// Disallows embedded expressions in event handler attributes, e.g. `onclick`,
+/// since their values are executed as JavaScript.
+fn check_event_handlers(nodes: &[ast::Node<ast::ArgValue>]) -> syn::Result<()> {
+    for_each_tag(nodes, &mut |tag, errors| {
+        if let ast::Tag::Opening { attrs, .. } = tag {
+            for attr in attrs {
+                let is_event_handler = attr
+                    .name
+                    .to_string()
+                    .to_ascii_lowercase()
+                    .starts_with("on");
+
+                if is_event_handler
+                    && attr.value.as_ref().is_some_and(|(_, v)| v.is_expr())
+                {
+                    errors.push(syn::Error::new(
+                        attr.span(),
+                        format_args!(
+                            "expressions are not allowed in event handler \
+                             attribute `{}`, use a string literal instead",
+                            attr.name
+                        ),
+                    ));
+                }
+            }
+        }
+    })
 }
 
+/// Disallows attributes with the same name on a tag, ignoring case.
+///
+/// Spread attributes are only known at runtime, so collisions with them are
+/// resolved when rendering: the static attributes take precedence over the
+/// pairs, and earlier pairs over later ones. Tags with both are warned about
+/// when lowering, see `lower_ast::collision_warning`.
 fn check_duplicate_attrs<V: Spanned>(
     nodes: &[ast::Node<V>],
 ) -> syn::Result<()> {
-    for node in nodes {
-        if let ast::Node::Tag(ast::Tag::Opening { attrs, .. }) = node {
-            for attr in attrs.iter().rev() {
-                if attrs.iter().filter(|a| attr.name == a.name).count() > 1 {
-                    return Err(syn::Error::new(
+    for_each_tag(nodes, &mut |tag, errors| {
+        if let ast::Tag::Opening { attrs, .. } = tag {
+            for (i, attr) in attrs.iter().enumerate() {
+                let name = attr.name.to_string();
+                if attrs[..i]
+                    .iter()
+                    .any(|a| a.name.to_string().eq_ignore_ascii_case(&name))
+                {
+                    errors.push(syn::Error::new(
                         attr.span(),
                         format_args!("duplicate attribute `{}`", attr.name),
                     ));
                 }
             }
         }
+    })
+}
+
+/// Checks for common accessibility problems: images without alternative text,
+/// links without a destination, form controls without a label, and invalid
+/// WAI-ARIA roles and attributes.
+///
+/// Spread attributes are only known at runtime, so attributes required on a
+/// tag with spread attributes are not checked.
+fn check_a11y<V: ast::Value>(nodes: &[ast::Node<V>]) -> syn::Result<()> {
+    let mut labels = Labels::default();
+    labels.collect(nodes);
+    check_a11y_nodes(nodes, &[], &labels)
+}
+
+/// The ids referred to by the `for` attributes of `<label>` elements.
+#[derive(Default)]
+struct Labels {
+    ids: Vec<String>,
+    /// Whether a `for` attribute is an expression, which could refer to any
+    /// id.
+    dynamic: bool,
+}
+
+impl Labels {
+    fn collect<V: ast::Value>(&mut self, nodes: &[ast::Node<V>]) {
+        for node in nodes {
+            if let ast::Node::Tag(ast::Tag::Opening { name, attrs, .. }) = node
+            {
+                if name.to_string().eq_ignore_ascii_case("label") {
+                    match find_attr(attrs, "for").map(attr_lit_str) {
+                        Some(Some(lit)) => self.ids.push(lit.value()),
+                        Some(None) => self.dynamic = true,
+                        None => {}
+                    }
+                }
+            }
+            for branch in node.branches() {
+                self.collect(branch);
+            }
+        }
     }
 
-    Ok(())
+    /// Returns `true` if a label could refer to the id.
+    fn refers_to(&self, id: &str) -> bool {
+        self.dynamic || self.ids.iter().any(|label_id| label_id == id)
+    }
+}
+
+fn check_a11y_nodes<V: ast::Value>(
+    nodes: &[ast::Node<V>],
+    parents: &[String],
+    labels: &Labels,
+) -> syn::Result<()> {
+    let mut stack = parents.to_vec();
+    let mut errors = Errors::default();
+
+    for node in nodes {
+        match node {
+            ast::Node::Tag(ast::Tag::Opening {
+                name,
+                attrs,
+                spreads,
+                self_closing_slash,
+            }) => {
+                let tag = name.to_string().to_ascii_lowercase();
+                check_aria_attrs(attrs, &mut errors);
+                if spreads.is_empty() {
+                    let in_label = stack.iter().any(|parent| parent == "label");
+                    check_required_attrs(
+                        name,
+                        &tag,
+                        attrs,
+                        in_label,
+                        labels,
+                        &mut errors,
+                    );
+                }
+                if self_closing_slash.is_none() && !html::is_void_element(&tag)
+                {
+                    stack.push(tag);
+                }
+            }
+            ast::Node::Tag(ast::Tag::Closing { .. }) => {
+                stack.pop();
+            }
+            _ => {
+                for branch in node.branches() {
+                    errors.check(check_a11y_nodes(branch, &stack, labels));
+                }
+            }
+        }
+    }
+
+    errors.finish()
+}
+
+/// Disallows unknown `aria-*` attributes and invalid roles.
+fn check_aria_attrs<V: ast::Value>(
+    attrs: &[ast::Attr<V>],
+    errors: &mut Errors,
+) {
+    for attr in attrs {
+        let attr_name = attr.name.to_string().to_ascii_lowercase();
+        if attr_name.starts_with("aria-") && !html::is_aria_attr(&attr_name) {
+            errors.push(match html::suggest_aria_attr(&attr_name) {
+                Some(suggestion) => syn::Error::new(
+                    attr.name.span(),
+                    format_args!(
+                        "unknown ARIA attribute `{}`, did you mean `{}`?",
+                        attr.name, suggestion
+                    ),
+                ),
+                None => syn::Error::new(
+                    attr.name.span(),
+                    format_args!("unknown ARIA attribute `{}`", attr.name),
+                ),
+            });
+        }
+
+        // A role can be followed by fallback roles, separated by spaces.
+        let role_lit = attr_lit_str(attr).filter(|_| attr_name == "role");
+        if let Some(lit) = role_lit {
+            for role in lit.value().split_ascii_whitespace() {
+                if html::is_aria_role(role) {
+                    continue;
+                }
+                errors.push(match html::suggest_aria_role(role) {
+                    Some(suggestion) => syn::Error::new(
+                        lit.span(),
+                        format_args!(
+                            "invalid role `{}`, did you mean `{}`?",
+                            role, suggestion
+                        ),
+                    ),
+                    None => syn::Error::new(
+                        lit.span(),
+                        format_args!("invalid role `{}`", role),
+                    ),
+                });
+            }
+        }
+    }
+}
+
+/// Requires alternative text on images, a destination on links, and a label
+/// on form controls.
+fn check_required_attrs<V: ast::Value>(
+    name: &ast::DashIdent,
+    tag: &str,
+    attrs: &[ast::Attr<V>],
+    in_label: bool,
+    labels: &Labels,
+    errors: &mut Errors,
+) {
+    match tag {
+        "img" if find_attr(attrs, "alt").is_none() => {
+            errors.push(syn::Error::new(
+                name.span(),
+                format_args!(
+                    "<{}> must have an `alt` attribute, use `alt=\"\"` for \
+                     decorative images",
+                    name
+                ),
+            ));
+        }
+        "a" if find_attr(attrs, "href").is_none() => {
+            errors.push(syn::Error::new(
+                name.span(),
+                format_args!(
+                    "<{}> must have an `href` attribute, use a <button> for \
+                     actions",
+                    name
+                ),
+            ));
+        }
+        "input" | "select" | "textarea" => {
+            // Buttons are labelled by their value, and hidden inputs are not
+            // rendered. The type of an input is unknown if it is an
+            // expression.
+            let needs_label = tag != "input"
+                || find_attr(attrs, "type").map_or(true, |attr| {
+                    attr_lit_str(attr).is_some_and(|lit| {
+                        !matches!(
+                            lit.value().to_ascii_lowercase().as_str(),
+                            "hidden" | "submit" | "reset" | "button" | "image"
+                        )
+                    })
+                });
+            let labelled = in_label
+                || find_attr(attrs, "aria-label").is_some()
+                || find_attr(attrs, "aria-labelledby").is_some()
+                || find_attr(attrs, "id").is_some_and(|id| {
+                    attr_lit_str(id)
+                        .map_or(true, |lit| labels.refers_to(&lit.value()))
+                });
+            if needs_label && !labelled {
+                errors.push(syn::Error::new(
+                    name.span(),
+                    format_args!(
+                        "<{}> must have an associated <label>, or an \
+                         `aria-label` or `aria-labelledby` attribute",
+                        name
+                    ),
+                ));
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Returns the attribute with the name, ignoring case.
+fn find_attr<'a, V>(
+    attrs: &'a [ast::Attr<V>],
+    name: &str,
+) -> Option<&'a ast::Attr<V>> {
+    attrs
+        .iter()
+        .find(|attr| attr.name.to_string().eq_ignore_ascii_case(name))
+}
+
+/// Returns the value of the attribute if it is a string literal.
+fn attr_lit_str<V: ast::Value>(attr: &ast::Attr<V>) -> Option<&syn::LitStr> {
+    attr.value
+        .as_ref()
+        .and_then(|(_, value)| value.as_lit_str())
+}
+
+/// Disallows props that cannot be passed as struct fields to components, and
+/// duplicate named slots in components and function calls.
+fn check_components<V: ast::Value>(nodes: &[ast::Node<V>]) -> syn::Result<()> {
+    let mut errors = Errors::default();
+
+    for node in nodes {
+        match node {
+            ast::Node::Component(component) => {
+                check_component(component, &mut errors);
+                check_slots(&component.children, &mut errors);
+            }
+            ast::Node::Call(call) => {
+                if let Some(children) = &call.children {
+                    check_slots(children, &mut errors);
+                }
+            }
+            _ => {}
+        }
+        for branch in node.branches() {
+            errors.check(check_components(branch));
+        }
+    }
+
+    errors.finish()
+}
+
+fn check_component<V: ast::Value>(
+    component: &ast::Component<V>,
+    errors: &mut Errors,
+) {
+    for (i, attr) in component.attrs.iter().enumerate() {
+        if attr.name.as_ident().is_none() {
+            errors.push(syn::Error::new(
+                attr.name.span(),
+                "component props must be identifiers",
+            ));
+        }
+        if let Some(optional) = attr.optional {
+            errors.push(syn::Error::new(
+                optional.span,
+                "optional attributes are not supported on components",
+            ));
+        }
+        match &attr.value {
+            Some((_, ast::AttrValue::List(list))) => {
+                errors.push(syn::Error::new(
+                    list.bracket.span.join(),
+                    "class lists are not supported on components",
+                ));
+            }
+            Some((_, ast::AttrValue::Single(value))) => {
+                if let Some(specs) = value.specs() {
+                    errors.push(syn::Error::new_spanned(
+                        specs,
+                        "formatting specifiers are not supported in component \
+                         props",
+                    ));
+                }
+            }
+            None => {}
+        }
+        if component.attrs[..i].iter().any(|a| attr.name == a.name) {
+            errors.push(syn::Error::new(
+                attr.name.span(),
+                format_args!("duplicate prop `{}`", attr.name),
+            ));
+        }
+    }
+
+    for spread in component.spreads.iter().skip(1) {
+        errors.push(syn::Error::new(
+            spread.span(),
+            "components accept at most one spread",
+        ));
+    }
+}
+
+fn check_slots<V>(children: &ast::Children<V>, errors: &mut Errors) {
+    for (i, slot) in children.slots.iter().enumerate() {
+        if children.slots[..i].iter().any(|s| slot.name == s.name) {
+            errors.push(syn::Error::new(
+                slot.name.span(),
+                format_args!("duplicate slot `{}`", slot.name),
+            ));
+        }
+    }
+}
+
+/// Disallows blocks with the same name, including nested blocks, since a
+/// block is overridden by its name.
+fn check_blocks<V>(nodes: &[ast::Node<V>]) -> syn::Result<()> {
+    fn collect<'a, V>(
+        nodes: &'a [ast::Node<V>],
+        names: &mut Vec<&'a syn::Ident>,
+    ) {
+        for node in nodes {
+            if let ast::Node::Block(block) = node {
+                names.push(&block.name);
+            }
+            for branch in node.branches() {
+                collect(branch, names);
+            }
+        }
+    }
+
+    let mut names = Vec::new();
+    collect(nodes, &mut names);
+    let mut errors = Errors::default();
+    for (i, name) in names.iter().enumerate() {
+        if names[..i].contains(name) {
+            errors.push(syn::Error::new(
+                name.span(),
+                format_args!("duplicate block `{}`", name),
+            ));
+        }
+    }
+
+    errors.finish()
+}
+
+/// Checks that standard HTML elements are nested according to their content
+/// model, since browsers re-parent misnested elements.
+///
+/// `parents` are the names of the open elements, and are unknown for the
+/// children of components and function calls.
+fn check_content_model<V: ast::Value>(
+    nodes: &[ast::Node<V>],
+    parents: &[String],
+) -> syn::Result<()> {
+    let mut stack = parents.to_vec();
+    let mut errors = Errors::default();
+
+    for node in nodes {
+        match node {
+            ast::Node::Tag(ast::Tag::Opening {
+                name,
+                attrs,
+                self_closing_slash,
+                ..
+            }) => {
+                let tag = name.to_string().to_ascii_lowercase();
+                check_element(name, &tag, attrs, &stack, &mut errors);
+                if self_closing_slash.is_none() && !html::is_void_element(&tag)
+                {
+                    stack.push(tag);
+                }
+            }
+            ast::Node::Tag(ast::Tag::Closing { .. }) => {
+                stack.pop();
+            }
+            ast::Node::Component(_) | ast::Node::Call(_) => {
+                for branch in node.branches() {
+                    errors.check(check_content_model(branch, &[]));
+                }
+            }
+            _ => {
+                for branch in node.branches() {
+                    errors.check(check_content_model(branch, &stack));
+                }
+            }
+        }
+    }
+
+    errors.finish()
+}
+
+fn check_element<V: ast::Value>(
+    name: &ast::DashIdent,
+    tag: &str,
+    attrs: &[ast::Attr<V>],
+    parents: &[String],
+    errors: &mut Errors,
+) {
+    // Elements can be placed anywhere within unknown elements and templates,
+    // e.g. custom elements.
+    let parent = parents
+        .last()
+        .filter(|parent| html::is_element(parent) && *parent != "template");
+    if let (Some(permitted), Some(parent)) =
+        (html::permitted_parents(tag), parent)
+    {
+        if !permitted.contains(&parent.as_str()) {
+            errors.push(syn::Error::new(
+                name.span(),
+                format_args!(
+                    "<{}> must be a child of {}, found <{}>",
+                    tag,
+                    list_elements(permitted),
+                    parent
+                ),
+            ));
+        }
+    }
+
+    if html::closes_p(tag) {
+        let p = parents
+            .iter()
+            .rev()
+            .take_while(|parent| !html::is_scope_boundary(parent))
+            .any(|parent| parent == "p");
+        if p {
+            errors.push(syn::Error::new(
+                name.span(),
+                format_args!("<{}> cannot be inside <p>", tag),
+            ));
+        }
+    }
+
+    let is_hidden_input = tag == "input"
+        && attrs.iter().any(|attr| {
+            attr.name.to_string().eq_ignore_ascii_case("type")
+                && matches!(
+                    &attr.value,
+                    Some((_, ast::AttrValue::Single(value)))
+                        if value.as_lit_str().is_some_and(|lit| {
+                            lit.value().eq_ignore_ascii_case("hidden")
+                        })
+                )
+        });
+    if html::is_interactive(tag) && !is_hidden_input {
+        if let Some(parent) = parents
+            .iter()
+            .rev()
+            .find(|parent| *parent == "a" || *parent == "button")
+        {
+            errors.push(syn::Error::new(
+                name.span(),
+                format_args!(
+                    "interactive <{}> cannot be inside <{}>",
+                    tag, parent
+                ),
+            ));
+        }
+    }
+}
+
+/// Disallows element and attribute names that are not defined by the HTML
+/// standard, suggesting the closest standard name for likely typos.
+///
+/// Custom elements, `<svg>` and `<math>` elements and their descendants are
+/// not checked, since they have their own names and attributes.
+fn check_names<V>(
+    nodes: &[ast::Node<V>],
+    parents: &[String],
+) -> syn::Result<()> {
+    let mut stack = parents.to_vec();
+    let mut errors = Errors::default();
+
+    for node in nodes {
+        match node {
+            ast::Node::Tag(ast::Tag::Opening {
+                name,
+                attrs,
+                self_closing_slash,
+                ..
+            }) => {
+                let tag = name.to_string().to_ascii_lowercase();
+                let is_foreign = html::is_foreign_element(&tag)
+                    || stack.iter().any(|p| html::is_foreign_element(p));
+                if !(is_foreign || html::is_custom_element(&tag)) {
+                    check_element_names(name, &tag, attrs, &mut errors);
+                }
+                if self_closing_slash.is_none() && !html::is_void_element(&tag)
+                {
+                    stack.push(tag);
+                }
+            }
+            ast::Node::Tag(ast::Tag::Closing { .. }) => {
+                stack.pop();
+            }
+            _ => {
+                for branch in node.branches() {
+                    errors.check(check_names(branch, &stack));
+                }
+            }
+        }
+    }
+
+    errors.finish()
+}
+
+fn check_element_names<V>(
+    name: &ast::DashIdent,
+    tag: &str,
+    attrs: &[ast::Attr<V>],
+    errors: &mut Errors,
+) {
+    if !html::is_element(tag) {
+        errors.push(match html::suggest_element(tag) {
+            Some(suggestion) => syn::Error::new(
+                name.span(),
+                format_args!(
+                    "unknown element <{}>, did you mean <{}>?",
+                    name, suggestion
+                ),
+            ),
+            None => syn::Error::new(
+                name.span(),
+                format_args!(
+                    "unknown element <{}>, custom element names must contain \
+                     a dash",
+                    name
+                ),
+            ),
+        });
+        return;
+    }
+
+    for attr in attrs {
+        let attr_name = attr.name.to_string();
+        if html::is_attr(&attr_name) {
+            continue;
+        }
+        errors.push(match html::suggest_attr(&attr_name) {
+            Some(suggestion) => syn::Error::new(
+                attr.name.span(),
+                format_args!(
+                    "unknown attribute `{}`, did you mean `{}`?",
+                    attr_name, suggestion
+                ),
+            ),
+            None => syn::Error::new(
+                attr.name.span(),
+                format_args!("unknown attribute `{}`", attr_name),
+            ),
+        });
+    }
+}
+
+/// Formats element names as a list, e.g. `<ul>, <ol> or <menu>`.
+fn list_elements(names: &[&str]) -> String {
+    let mut list = String::new();
+    for (i, name) in names.iter().enumerate() {
+        if i > 0 {
+            list.push_str(if i == names.len() - 1 { " or " } else { ", " });
+        }
+        list.push_str(&format!("<{}>", name));
+    }
+    list
 }
 
 fn check_node_tree<V: Spanned>(nodes: &[ast::Node<V>]) -> syn::Result<()> {
     let mut stack = Vec::new();
+    let mut errors = Errors::default();
+    // Once the tags are unbalanced, every following closing tag would be
+    // reported as well, so the tags are no longer matched.
+    let mut balanced = true;
 
     for node in nodes {
+        // Every branch, and the children of every component, must be
+        // balanced on their own.
+        for branch in node.branches() {
+            errors.check(check_node_tree(branch));
+        }
+
         if let ast::Node::Tag(tag) = node {
             match &tag {
-                ast::Tag::Opening { .. } => {
-                    if !tag.is_self_closing() {
+                ast::Tag::Opening {
+                    name,
+                    self_closing_slash,
+                    ..
+                } => {
+                    let name = name.to_string();
+                    // Void elements have no closing tag, with or without a
+                    // slash, while other HTML elements must be closed.
+                    // Unknown elements, e.g. SVG elements, may self-close.
+                    if let Some(slash) = self_closing_slash {
+                        if html::is_element(&name)
+                            && !html::is_void_element(&name)
+                        {
+                            errors.push(syn::Error::new(
+                                slash.span,
+                                format_args!(
+                                    "<{}> is not a void element and cannot \
+                                     be self-closing",
+                                    name
+                                ),
+                            ));
+                        }
+                    } else if balanced && !html::is_void_element(&name) {
                         stack.push(tag);
                     }
                 }
+                ast::Tag::Closing { name }
+                    if html::is_void_element(&name.to_string()) =>
+                {
+                    errors.push(syn::Error::new(
+                        name.span(),
+                        format_args!(
+                            "<{}> is a void element and cannot have a closing \
+                             tag",
+                            name
+                        ),
+                    ));
+                }
+                ast::Tag::Closing { .. } if !balanced => {}
                 ast::Tag::Closing { name } => {
                     if let Some(stack_tag) = stack.pop() {
                         if name != stack_tag.name() {
-                            return Err(syn::Error::new(
+                            errors.push(syn::Error::new(
                                 tag.span(),
                                 format_args!(
                                     "closing tag mismatch, expected </{}>, \
@@ -52,9 +781,10 @@ fn check_node_tree<V: Spanned>(nodes: &[ast::Node<V>]) -> syn::Result<()> {
                                     name,
                                 ),
                             ));
+                            balanced = false;
                         }
                     } else {
-                        return Err(syn::Error::new(
+                        errors.push(syn::Error::new(
                             tag.span(),
                             format_args!(
                                 "closing tag has no corresponding opening \
@@ -62,14 +792,15 @@ fn check_node_tree<V: Spanned>(nodes: &[ast::Node<V>]) -> syn::Result<()> {
                                 name
                             ),
                         ));
+                        balanced = false;
                     }
                 }
             }
         }
     }
 
-    if let Some(stack_tag) = stack.pop() {
-        return Err(syn::Error::new(
+    if let Some(stack_tag) = stack.pop().filter(|_| balanced) {
+        errors.push(syn::Error::new(
             stack_tag.span(),
             format_args!(
                 "opening tag has no corresponding closing </{}> tag",
@@ -78,7 +809,7 @@ fn check_node_tree<V: Spanned>(nodes: &[ast::Node<V>]) -> syn::Result<()> {
         ));
     }
 
-    Ok(())
+    errors.finish()
 }
 
 #[cfg(test)]
@@ -101,6 +832,7 @@ mod tests {
             ast::Node::Tag(ast::Tag::<ast::LitValue>::Opening {
                 name: dash_ident!(foo),
                 attrs: vec![],
+                spreads: vec![],
                 self_closing_slash: None,
             }),
             ast::Node::Tag(ast::Tag::<ast::LitValue>::Closing {
@@ -118,6 +850,7 @@ mod tests {
             ast::Tag::<ast::LitValue>::Opening {
                 name: dash_ident!(foo),
                 attrs: vec![],
+                spreads: vec![],
                 self_closing_slash: Some(syn::Token![/]([Span::call_site()])),
             },
         )])
@@ -130,6 +863,7 @@ mod tests {
             ast::Tag::<ast::LitValue>::Opening {
                 name: dash_ident!(foo),
                 attrs: vec![],
+                spreads: vec![],
                 self_closing_slash: None,
             },
         )])
@@ -152,6 +886,7 @@ mod tests {
             ast::Node::Tag(ast::Tag::<ast::LitValue>::Opening {
                 name: dash_ident!(foo),
                 attrs: vec![],
+                spreads: vec![],
                 self_closing_slash: None,
             }),
             ast::Node::Tag(ast::Tag::<ast::LitValue>::Closing {
@@ -163,6 +898,257 @@ mod tests {
         );
     }
 
+    #[test]
+    fn unbalanced_branch() {
+        let nodes = syn::parse2::<ast::If<ast::LitValue>>(quote::quote! {
+            if true { <div> } @else { </div> }
+        })
+        .map(|if_| {
+            [ast::Node::ControlFlow(Box::new(ast::ControlFlow::If(if_)))]
+        })
+        .unwrap();
+
+        check_node_tree(&nodes)
+            .expect_err("a branch with an unclosed tag should be disallowed");
+    }
+
+    #[test]
+    fn balanced_branches() {
+        let nodes = syn::parse2::<ast::For<ast::LitValue>>(quote::quote! {
+            for x in xs { <li>{x}</li> }
+        })
+        .map(|for_| {
+            [ast::Node::ControlFlow(Box::new(ast::ControlFlow::For(
+                for_,
+            )))]
+        })
+        .unwrap();
+
+        check_node_tree(&nodes).expect("a balanced branch should be allowed");
+    }
+
+    #[test]
+    fn event_handler_attrs() {
+        let tag = |value| {
+            ast::Node::Tag(ast::Tag::Opening {
+                name: dash_ident!(button),
+                attrs: vec![ast::Attr {
+                    name: dash_ident!(onclick),
+                    optional: None,
+                    value: Some((
+                        syn::Token![=]([Span::call_site()]),
+                        ast::AttrValue::Single(value),
+                    )),
+                }],
+                spreads: vec![],
+                self_closing_slash: None,
+            })
+        };
+
+        check_event_handlers(&[tag(ast::ArgValue::LitStr(syn::LitStr::new(
+            "alert(1)",
+            Span::call_site(),
+        )))])
+        .expect("a string literal event handler should be allowed");
+
+        check_event_handlers(&[tag(ast::ArgValue::Expr {
+            value: syn::parse_quote!(handler),
+            specs: None,
+        })])
+        .expect_err("an expression event handler should be disallowed");
+    }
+
+    #[test]
+    fn void_elements() {
+        let check = |tokens| {
+            syn::parse::Parser::parse2(crate::parse::parse_template, tokens)
+        };
+
+        check(quote::quote!(<p>"a"<br>"b"<br /><img src="x" alt=""></p>))
+            .expect("void elements with and without a slash should be allowed");
+        check(quote::quote!(<br></br>))
+            .expect_err("closing tags of void elements should be disallowed");
+        check(quote::quote!(<div />))
+            .expect_err("self-closing non-void elements should be disallowed");
+        check(quote::quote!(<svg><path d="M0" /></svg>))
+            .expect("self-closing unknown elements should be allowed");
+    }
+
+    #[test]
+    fn content_model() {
+        let check = |tokens: proc_macro2::TokenStream| {
+            syn::parse::Parser::parse2(
+                crate::parse::parse_template,
+                quote::quote!(#![fhtml(strict)] #tokens),
+            )
+        };
+
+        check(quote::quote! {
+            <ul>@for item in items { <li>{item}</li> }</ul>
+            <table><tbody><tr><td>"a"</td></tr></tbody></table>
+            <p><span>"a"</span><a href="/">"b"</a></p>
+            <button><input type="hidden" name="x">"c"</button>
+            <li>"fragment"</li>
+            <Card><li>"child"</li></Card>
+        })
+        .expect("valid nesting should be allowed");
+
+        check(quote::quote!(<div><li>"a"</li></div>))
+            .expect_err("<li> outside of a list should be disallowed");
+        check(quote::quote!(<table><tr><td>"a"</td></tr></table>))
+            .expect_err("<tr> directly in <table> should be disallowed");
+        check(quote::quote!(<p><span><div>"a"</div></span></p>))
+            .expect_err("block elements in <p> should be disallowed");
+        check(quote::quote!(<a href="/"><button>"a"</button></a>))
+            .expect_err("interactive content in <a> should be disallowed");
+        check(quote::quote!(<button>@if x { <input> }</button>))
+            .expect_err("interactive content in branches should be checked");
+
+        syn::parse::Parser::parse2(
+            crate::parse::parse_template,
+            quote::quote!(<div><li>"a"</li></div>),
+        )
+        .expect("nesting should only be checked in strict mode");
+    }
+
+    #[test]
+    fn reports_all_errors() {
+        let errors = syn::parse::Parser::parse2(
+            crate::parse::parse_template,
+            quote::quote! {
+                <div><span></div>
+                <p><br></br><div /></p>
+                <a onclick={x} href="" href="">@if x { <ul> }</a>
+                <Card x-y="" a="" a="" />
+            },
+        )
+        .expect_err("invalid nodes should be disallowed");
+        assert_eq!(errors.into_iter().count(), 8);
+    }
+
+    #[test]
+    fn names() {
+        let check = |tokens: proc_macro2::TokenStream| {
+            syn::parse::Parser::parse2(
+                crate::parse::parse_template,
+                quote::quote!(#![fhtml(lint)] #tokens),
+            )
+        };
+
+        check(quote::quote! {
+            <div class="a" data-id="1" aria-label="b" onclick="f()">
+                <my-element foo="bar"></my-element>
+                <svg viewBox="0 0 1 1"><path d="M0" /></svg>
+                <Card anything="x" />
+                @if x { <input type="text" aria-label="x" required> }
+            </div>
+        })
+        .expect("standard and custom names should be allowed");
+
+        let message = |tokens| check(tokens).unwrap_err().to_string();
+        assert_eq!(
+            message(quote::quote!(<dvi></dvi>)),
+            "unknown element <dvi>, did you mean <div>?"
+        );
+        assert_eq!(
+            message(quote::quote!(<p clas="a"></p>)),
+            "unknown attribute `clas`, did you mean `class`?"
+        );
+        assert_eq!(
+            message(quote::quote!(<widget></widget>)),
+            "unknown element <widget>, custom element names must contain a \
+             dash"
+        );
+        check(quote::quote!(@for x in xs { <p hx-get="/"></p> }))
+            .expect_err("names in branches should be checked");
+
+        syn::parse::Parser::parse2(
+            crate::parse::parse_template,
+            quote::quote!(<dvi clas="a"></dvi>),
+        )
+        .expect("names should only be checked with the lint option");
+    }
+
+    #[test]
+    fn a11y() {
+        let check = |tokens: proc_macro2::TokenStream| {
+            syn::parse::Parser::parse2(
+                crate::parse::parse_template,
+                quote::quote!(#![fhtml(a11y)] #tokens),
+            )
+        };
+
+        check(quote::quote! {
+            <img src="a.png" alt="">
+            <a href="/" role="button" aria-pressed="false">"a"</a>
+            <label>"Name" <input type="text" name="name"></label>
+            <label for="email">"Email"</label><input id="email" type="email">
+            <input type="search" aria-label="Search">
+            <input type="hidden" name="token" value="x">
+            <input type="submit">
+            <img ..{attrs}>
+            <div role="none presentation"></div>
+        })
+        .expect("accessible markup should be allowed");
+
+        let message = |tokens| check(tokens).unwrap_err().to_string();
+        assert_eq!(
+            message(quote::quote!(<img src="a.png">)),
+            "<img> must have an `alt` attribute, use `alt=\"\"` for \
+             decorative images"
+        );
+        assert_eq!(
+            message(quote::quote!(<a>"a"</a>)),
+            "<a> must have an `href` attribute, use a <button> for actions"
+        );
+        assert_eq!(
+            message(quote::quote!(<div role="buton"></div>)),
+            "invalid role `buton`, did you mean `button`?"
+        );
+        assert_eq!(
+            message(quote::quote!(<div aria-lable="a"></div>)),
+            "unknown ARIA attribute `aria-lable`, did you mean `aria-label`?"
+        );
+        check(quote::quote!(<label for="a">"A"</label><input id="b">))
+            .expect_err("a label for another id should be disallowed");
+        check(quote::quote!(@if x { <select></select> }))
+            .expect_err("form controls in branches should be checked");
+
+        syn::parse::Parser::parse2(
+            crate::parse::parse_template,
+            quote::quote!(<img src="a.png"><a></a>),
+        )
+        .expect("accessibility should only be checked with the a11y option");
+    }
+
+    #[test]
+    fn component_props() {
+        let check = |tokens| {
+            let node = syn::parse2::<ast::Node<ast::ArgValue>>(tokens).unwrap();
+            check_components(&[node])
+        };
+
+        check(quote::quote!(<Card title="x" open />))
+            .expect("props with and without values should be allowed");
+        check(quote::quote!(<Card data-x="x" />))
+            .expect_err("props with dashes should be disallowed");
+        check(quote::quote!(<Card title?={title} />))
+            .expect_err("optional props should be disallowed");
+        check(quote::quote!(<Card title={title:?} />)).expect_err(
+            "props with formatting specifiers should be disallowed",
+        );
+        check(quote::quote!(<Card title="x" title="y" />))
+            .expect_err("duplicate props should be disallowed");
+        check(quote::quote! {
+            <Card><slot:a></slot:a><slot:a></slot:a></Card>
+        })
+        .expect_err("duplicate slots should be disallowed");
+        check(quote::quote! {
+            @layout() { <slot:a></slot:a><slot:a></slot:a> }
+        })
+        .expect_err("duplicate slots should be disallowed");
+    }
+
     #[test]
     fn duplicate_attrs() {
         check_duplicate_attrs(&[ast::Node::Tag(ast::Tag::Opening {
@@ -170,23 +1156,33 @@ mod tests {
             attrs: vec![
                 ast::Attr {
                     name: dash_ident!(bar),
-                    eq_sep: syn::Token![=]([Span::call_site()]),
-                    value: ast::LitValue::LitStr(syn::LitStr::new(
-                        "",
-                        Span::call_site(),
+                    optional: None,
+                    value: Some((
+                        syn::Token![=]([Span::call_site()]),
+                        ast::AttrValue::Single(ast::LitValue::LitStr(
+                            syn::LitStr::new("", Span::call_site()),
+                        )),
                     )),
                 },
                 ast::Attr {
                     name: dash_ident!(bar),
-                    eq_sep: syn::Token![=]([Span::call_site()]),
-                    value: ast::LitValue::LitStr(syn::LitStr::new(
-                        "",
-                        Span::call_site(),
+                    optional: None,
+                    value: Some((
+                        syn::Token![=]([Span::call_site()]),
+                        ast::AttrValue::Single(ast::LitValue::LitStr(
+                            syn::LitStr::new("", Span::call_site()),
+                        )),
                     )),
                 },
             ],
+            spreads: vec![],
             self_closing_slash: Some(syn::Token![/]([Span::call_site()])),
         })])
         .expect_err("duplicate attribute should be disallowed");
+        syn::parse::Parser::parse2(
+            crate::parse::parse_template,
+            quote::quote!(<p id="a" ID="b"></p>),
+        )
+        .expect_err("attribute names should be compared ignoring case");
     }
 }
@@ -3,6 +3,8 @@ use quote::ToTokens;
 use syn::punctuated::Punctuated;
 use syn::spanned::Spanned;
 
+use crate::html;
+
 // An identifier separated by dashes, `foo-bar-baz`.
 #[derive(PartialEq, Clone, Debug)]
 pub(crate) struct DashIdent(pub Punctuated<syn::Ident, syn::Token![-]>);
@@ -10,11 +12,18 @@ pub(crate) struct DashIdent(pub Punctuated<syn::Ident, syn::Token![-]>);
 #[derive(PartialEq, Clone, Debug)]
 pub(crate) struct Doctype;
 
+/// A comment, `<!-- "text" -->`.
+#[derive(PartialEq, Clone, Debug)]
+pub(crate) struct Comment {
+    pub text: syn::LitStr,
+}
+
 /// A value that is either a string literal or an expression.
 ///
 /// This is the most straight-forward value type, used in `fhtml::concat!` and
 /// tests.
 #[derive(Clone, Debug, PartialEq)]
+#[allow(clippy::large_enum_variant)]
 pub(crate) enum LitValue {
     LitStr(syn::LitStr),
     Expr(syn::Expr),
@@ -22,12 +31,13 @@ pub(crate) enum LitValue {
 
 /// A value that represents an argument, usually used in formatting contexts.
 ///
-/// The string representation of this value is a placeholder `{}`, that may
-/// contain formatting specifiers `{:?}`.
+/// The string representation of this value is a placeholder `{}`.
 ///
 /// The token representation of this value is the actual Rust value it contains,
-/// either `LitStr` or `Expr`.
+/// either `LitStr` or `Expr`. See `ArgValue::into_arg` for the argument that
+/// renders the value.
 #[derive(Clone, Debug)]
+#[allow(clippy::large_enum_variant)]
 pub(crate) enum ArgValue {
     LitStr(syn::LitStr),
     Expr {
@@ -36,11 +46,47 @@ pub(crate) enum ArgValue {
     },
 }
 
+/// An attribute, `name = value`, or a bare attribute without a value, `name`.
+///
+/// An optional attribute, `name?={value}`, is omitted when the value is
+/// `None`.
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) struct Attr<V> {
     pub name: DashIdent,
-    pub eq_sep: syn::token::Eq,
+    pub optional: Option<syn::token::Question>,
+    pub value: Option<(syn::token::Eq, AttrValue<V>)>,
+}
+
+/// The value of an attribute, either a single value or a class list.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum AttrValue<V> {
+    Single(V),
+    List(ClassList<V>),
+}
+
+/// A list of class names, `["foo", "bar" => cond, {baz}]`.
+///
+/// Active class names are joined with single spaces, skipping empty and
+/// duplicate class names.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ClassList<V> {
+    pub bracket: syn::token::Bracket,
+    pub items: Punctuated<ClassItem<V>, syn::token::Comma>,
+}
+
+/// A class name in a class list, that is active if the condition is `true`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ClassItem<V> {
     pub value: V,
+    pub cond: Option<syn::Expr>,
+}
+
+/// Attributes spread from a runtime collection of name-value pairs,
+/// `..{attrs}`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Spread {
+    pub dot2: syn::token::DotDot,
+    pub expr: syn::Expr,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -48,6 +94,7 @@ pub(crate) enum Tag<V> {
     Opening {
         name: DashIdent,
         attrs: Vec<Attr<V>>,
+        spreads: Vec<Spread>,
         self_closing_slash: Option<syn::token::Slash>,
     },
     Closing {
@@ -55,22 +102,285 @@ pub(crate) enum Tag<V> {
     },
 }
 
+/// A component, `<Name prop={value}>...</Name>`, which is a struct
+/// implementing `fhtml::Component`.
+///
+/// The attributes are the fields of the struct, and the children are passed
+/// as the `children` field and a field for every named slot.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Component<V> {
+    pub name: syn::Ident,
+    pub attrs: Vec<Attr<V>>,
+    pub spreads: Vec<Spread>,
+    pub children: Children<V>,
+}
+
+/// A function call, `@path(args) { ... }`.
+///
+/// If there is a block, the children are passed as a trailing `fhtml::Slots`
+/// argument.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Call<V> {
+    pub func: syn::ExprPath,
+    pub args: Punctuated<syn::Expr, syn::token::Comma>,
+    pub children: Option<Children<V>>,
+}
+
+/// The children of a component or function call, which are lazily rendered.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Children<V> {
+    pub nodes: Vec<Node<V>>,
+    pub slots: Vec<NamedSlot<V>>,
+}
+
+/// A named slot, `<slot:name>...</slot:name>`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct NamedSlot<V> {
+    pub name: syn::Ident,
+    pub nodes: Vec<Node<V>>,
+}
+
+/// A named block, `@block name { ... }`.
+///
+/// In a layout, the nodes of the block are the default content, which is
+/// replaced by a template extending the layout.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Block<V> {
+    pub name: syn::Ident,
+    pub nodes: Vec<Node<V>>,
+}
+
+/// `@extends "path"`, followed by the blocks that are overridden in the
+/// layout at the path.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Extends<V> {
+    pub path: syn::LitStr,
+    /// Only `Node::Block`s.
+    pub blocks: Vec<Node<V>>,
+}
+
+/// A parsed template, which either consists of nodes, or extends a layout.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Template<V> {
+    Nodes(Vec<Node<V>>),
+    Extends(Extends<V>),
+}
+
+impl<V> Template<V> {
+    /// Returns the top-level nodes, i.e. the blocks of a template extending a
+    /// layout.
+    pub fn nodes_mut(&mut self) -> &mut Vec<Node<V>> {
+        match self {
+            Self::Nodes(nodes) => nodes,
+            Self::Extends(extends) => &mut extends.blocks,
+        }
+    }
+}
+
+/// `@if cond { ... } @else { ... }`
+///
+/// An `@else if` is represented as an `else_branch` containing a single `If`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct If<V> {
+    pub if_token: syn::token::If,
+    pub cond: syn::Expr,
+    pub then_branch: Vec<Node<V>>,
+    pub else_branch: Option<Vec<Node<V>>>,
+}
+
+/// `@for pat in expr { ... }`
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct For<V> {
+    pub for_token: syn::token::For,
+    pub pat: syn::Pat,
+    pub expr: syn::Expr,
+    pub body: Vec<Node<V>>,
+}
+
+/// `@match expr { pat => { ... } }`
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Match<V> {
+    pub match_token: syn::token::Match,
+    pub expr: syn::Expr,
+    pub arms: Vec<Arm<V>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Arm<V> {
+    pub pat: syn::Pat,
+    pub guard: Option<syn::Expr>,
+    pub body: Vec<Node<V>>,
+}
+
+/// `@let pat = expr;`
+///
+/// The binding is in scope for the rest of the nodes on the same level.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Let {
+    pub local: syn::Local,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum ControlFlow<V> {
+    If(If<V>),
+    For(For<V>),
+    Match(Match<V>),
+    Let(Let),
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) enum Node<V> {
     Doctype(Doctype),
+    Comment(Comment),
     Tag(Tag<V>),
+    Component(Box<Component<V>>),
+    Call(Box<Call<V>>),
+    Block(Block<V>),
     Value(V),
+    ControlFlow(Box<ControlFlow<V>>),
+}
+
+/// Common behavior of value types.
+pub(crate) trait Value: Sized {
+    /// Creates a value from a string literal.
+    fn from_lit_str(lit: syn::LitStr) -> Self;
+
+    /// Returns the string literal, if the value is one.
+    fn as_lit_str(&self) -> Option<&syn::LitStr>;
+
+    /// Returns `true` if the value is an expression, rather than a string
+    /// literal.
+    fn is_expr(&self) -> bool {
+        self.as_lit_str().is_none()
+    }
+
+    /// Returns the formatting specifiers of the value, if any.
+    fn specs(&self) -> Option<&TokenStream> {
+        None
+    }
+}
+
+impl Value for LitValue {
+    fn from_lit_str(lit: syn::LitStr) -> Self {
+        Self::LitStr(lit)
+    }
+
+    fn as_lit_str(&self) -> Option<&syn::LitStr> {
+        match self {
+            Self::LitStr(lit) => Some(lit),
+            Self::Expr(_) => None,
+        }
+    }
+}
+
+impl Value for ArgValue {
+    fn from_lit_str(lit: syn::LitStr) -> Self {
+        Self::LitStr(lit)
+    }
+
+    fn as_lit_str(&self) -> Option<&syn::LitStr> {
+        match self {
+            Self::LitStr(lit) => Some(lit),
+            Self::Expr { .. } => None,
+        }
+    }
+
+    fn specs(&self) -> Option<&TokenStream> {
+        match self {
+            Self::LitStr(_) => None,
+            Self::Expr { specs, .. } => specs.as_ref(),
+        }
+    }
+}
+
+impl<V: Value> Value for AttrValue<V> {
+    fn from_lit_str(lit: syn::LitStr) -> Self {
+        Self::Single(V::from_lit_str(lit))
+    }
+
+    fn as_lit_str(&self) -> Option<&syn::LitStr> {
+        match self {
+            Self::Single(value) => value.as_lit_str(),
+            Self::List(_) => None,
+        }
+    }
+}
+
+impl<V: Value> ClassList<V> {
+    /// Returns the class names if every item is an unconditional string
+    /// literal, without empty and duplicate class names.
+    pub(crate) fn to_static(&self) -> Option<Vec<String>> {
+        let mut names = Vec::<String>::new();
+        for item in &self.items {
+            if item.cond.is_some() {
+                return None;
+            }
+            for name in
+                item.value.as_lit_str()?.value().split_ascii_whitespace()
+            {
+                if !names.iter().any(|n| n == name) {
+                    names.push(name.to_owned());
+                }
+            }
+        }
+        Some(names)
+    }
 }
 
 impl DashIdent {
     pub(crate) fn span(&self) -> Span {
         self.0.span()
     }
+
+    /// Returns the identifier, if there are no dashes.
+    pub(crate) fn as_ident(&self) -> Option<&syn::Ident> {
+        match self.0.len() {
+            1 => self.0.first(),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if this is the name of a component rather than an HTML
+    /// element, i.e. a single identifier in `PascalCase`: starting with an
+    /// uppercase letter and containing a lowercase one, so that uppercase
+    /// element names like `<DIV>` are still elements. `<Svg>` and `<Math>` are
+    /// the foreign elements rather than components.
+    pub(crate) fn is_component(&self) -> bool {
+        self.as_ident().is_some_and(|ident| {
+            let name = ident.to_string();
+            name.starts_with(|c: char| c.is_ascii_uppercase())
+                && name.contains(|c: char| c.is_ascii_lowercase())
+                && !html::is_foreign_element(&name)
+        })
+    }
+}
+
+impl<V: Spanned> AttrValue<V> {
+    pub(crate) fn span(&self) -> Span {
+        match self {
+            Self::Single(value) => value.span(),
+            Self::List(list) => list.bracket.span.join(),
+        }
+    }
 }
 
 impl<V: Spanned> Attr<V> {
     pub(crate) fn span(&self) -> Span {
-        join_spans([self.name.span(), self.eq_sep.span(), self.value.span()])
+        let mut v = vec![self.name.span()];
+        if let Some(optional) = &self.optional {
+            v.push(optional.span);
+        }
+        if let Some((eq_sep, value)) = &self.value {
+            v.push(eq_sep.span);
+            v.push(value.span());
+        }
+        join_spans(v)
+    }
+}
+
+impl Spread {
+    pub(crate) fn span(&self) -> Span {
+        join_spans([self.dot2.spans[0], self.expr.span()])
     }
 }
 
@@ -81,16 +391,6 @@ impl<V> Tag<V> {
             Self::Closing { name } => name,
         }
     }
-
-    pub(crate) fn is_self_closing(&self) -> bool {
-        matches!(
-            self,
-            Self::Opening {
-                self_closing_slash: Some(_),
-                ..
-            }
-        )
-    }
 }
 
 impl<V: Spanned> Tag<V> {
@@ -99,13 +399,17 @@ impl<V: Spanned> Tag<V> {
             Tag::Opening {
                 name,
                 attrs,
+                spreads,
                 self_closing_slash,
             } => {
-                let mut v = Vec::with_capacity(2 + attrs.len());
+                let mut v = Vec::with_capacity(2 + attrs.len() + spreads.len());
                 v.push(name.span());
                 for attr in attrs {
                     v.push(attr.span());
                 }
+                for spread in spreads {
+                    v.push(spread.span());
+                }
                 if let Some(slash) = self_closing_slash {
                     v.push(slash.span());
                 }
@@ -116,26 +420,110 @@ impl<V: Spanned> Tag<V> {
     }
 }
 
-impl<V: Clone> Node<V> {
-    pub(crate) fn get_all_values(&self) -> Vec<V> {
-        let mut v = Vec::new();
+impl<V> ControlFlow<V> {
+    /// Returns the span of the keyword.
+    pub(crate) fn span(&self) -> Span {
+        match self {
+            Self::If(If { if_token, .. }) => if_token.span,
+            Self::For(For { for_token, .. }) => for_token.span,
+            Self::Match(Match { match_token, .. }) => match_token.span,
+            Self::Let(Let { local }) => local.let_token.span,
+        }
+    }
 
+    /// Returns the nodes of every branch.
+    pub(crate) fn branches(&self) -> Vec<&[Node<V>]> {
         match self {
-            Node::Tag(Tag::Opening { attrs, .. }) => {
-                for attr in attrs {
-                    v.push(attr.value.clone());
+            Self::If(If {
+                then_branch,
+                else_branch,
+                ..
+            }) => {
+                let mut v = vec![then_branch.as_slice()];
+                if let Some(else_branch) = else_branch {
+                    v.push(else_branch);
                 }
+                v
             }
-            Node::Value(value) => {
-                v.push(value.clone());
+            Self::For(For { body, .. }) => vec![body],
+            Self::Match(Match { arms, .. }) => {
+                arms.iter().map(|arm| arm.body.as_slice()).collect()
             }
-            _ => {}
+            Self::Let(_) => vec![],
         }
+    }
 
+    /// Returns the nodes of every branch, mutably.
+    pub(crate) fn branches_mut(&mut self) -> Vec<&mut Vec<Node<V>>> {
+        match self {
+            Self::If(If {
+                then_branch,
+                else_branch,
+                ..
+            }) => {
+                let mut v = vec![then_branch];
+                if let Some(else_branch) = else_branch {
+                    v.push(else_branch);
+                }
+                v
+            }
+            Self::For(For { body, .. }) => vec![body],
+            Self::Match(Match { arms, .. }) => {
+                arms.iter_mut().map(|arm| &mut arm.body).collect()
+            }
+            Self::Let(_) => vec![],
+        }
+    }
+}
+
+impl<V> Children<V> {
+    /// Returns the nodes of the children and every named slot.
+    pub(crate) fn branches(&self) -> Vec<&[Node<V>]> {
+        let mut v = vec![self.nodes.as_slice()];
+        v.extend(self.slots.iter().map(|slot| slot.nodes.as_slice()));
+        v
+    }
+
+    /// Returns the nodes of the children and every named slot, mutably.
+    pub(crate) fn branches_mut(&mut self) -> Vec<&mut Vec<Node<V>>> {
+        let mut v = vec![&mut self.nodes];
+        v.extend(self.slots.iter_mut().map(|slot| &mut slot.nodes));
         v
     }
 }
 
+impl<V> Node<V> {
+    /// Returns the nested nodes of control flow branches, blocks, and the
+    /// children of components and function calls.
+    pub(crate) fn branches(&self) -> Vec<&[Node<V>]> {
+        match self {
+            Self::ControlFlow(control_flow) => control_flow.branches(),
+            Self::Component(component) => component.children.branches(),
+            Self::Call(call) => call
+                .children
+                .as_ref()
+                .map_or_else(Vec::new, Children::branches),
+            Self::Block(block) => vec![&block.nodes],
+            _ => vec![],
+        }
+    }
+
+    /// Returns the nested nodes of control flow branches, blocks, and the
+    /// children of components and function calls, mutably.
+    pub(crate) fn branches_mut(&mut self) -> Vec<&mut Vec<Node<V>>> {
+        match self {
+            Self::ControlFlow(control_flow) => control_flow.branches_mut(),
+            Self::Component(component) => component.children.branches_mut(),
+            Self::Call(call) => call
+                .children
+                .as_mut()
+                .map_or_else(Vec::new, Children::branches_mut),
+            Self::Block(block) => vec![&mut block.nodes],
+            _ => vec![],
+        }
+    }
+}
+
 impl ToTokens for LitValue {
     fn to_tokens(&self, tokens: &mut TokenStream) {
         match self {
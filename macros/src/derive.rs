@@ -0,0 +1,88 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::{lower_ast, FormatArgsInput};
+
+/// Expands `#[derive(Render)]`, implementing `Display` and `fhtml::Render`
+/// with the template in the `#[template(...)]` attribute.
+pub(crate) fn derive_render(
+    input: syn::DeriveInput,
+) -> syn::Result<TokenStream> {
+    if let syn::Data::Union(data) = &input.data {
+        return Err(syn::Error::new(
+            data.union_token.span,
+            "`Render` cannot be derived for unions",
+        ));
+    }
+
+    let mut templates = input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("template"));
+
+    let template = templates.next().ok_or_else(|| {
+        syn::Error::new(
+            input.ident.span(),
+            "missing `#[template(...)]` attribute",
+        )
+    })?;
+    if let Some(attr) = templates.next() {
+        return Err(syn::Error::new_spanned(
+            attr,
+            "duplicate `#[template(...)]` attribute",
+        ));
+    }
+
+    let FormatArgsInput { nodes } = template.parse_args()?;
+    // The arguments are created each time the value is formatted.
+    let (fmt, args) =
+        lower_ast::lower_format_args(nodes, lower_ast::Uses::Once);
+
+    // The named fields of a struct are in scope in the template.
+    let fields = match &input.data {
+        syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Named(fields),
+            ..
+        }) => {
+            let idents = fields.named.iter().map(|field| &field.ident);
+            Some(quote! {
+                #[allow(unused_variables)]
+                let Self { #(#idents,)* } = self;
+            })
+        }
+        _ => None,
+    };
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) =
+        input.generics.split_for_impl();
+    let f = lower_ast::formatter();
+    let ctx = syn::Ident::new("ctx", proc_macro2::Span::mixed_site());
+
+    Ok(quote! {
+        impl #impl_generics ::core::fmt::Display for #ident #ty_generics
+        #where_clause
+        {
+            fn fmt(
+                &self,
+                #f: &mut ::core::fmt::Formatter<'_>,
+            ) -> ::core::fmt::Result {
+                #fields
+                #f.write_fmt(::core::format_args!(#fmt, #(#args),*))
+            }
+        }
+
+        impl #impl_generics ::fhtml::Render for #ident #ty_generics
+        #where_clause
+        {
+            #[inline]
+            fn render(
+                &self,
+                #f: &mut ::core::fmt::Formatter<'_>,
+                #ctx: ::fhtml::Context,
+            ) -> ::core::fmt::Result {
+                ::fhtml::__private::render_markup(self, #f, #ctx)
+            }
+        }
+    })
+}
@@ -22,17 +22,9 @@ impl fmt::Display for ast::LitValue {
 
 impl fmt::Display for ast::ArgValue {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::LitStr(_) => f.write_str("{}"),
-            Self::Expr { specs, .. } => {
-                f.write_char('{')?;
-                if let Some(specs) = specs {
-                    f.write_char(':')?;
-                    specs.to_string().replace(' ', "").fmt(f)?;
-                }
-                f.write_char('}')
-            }
-        }
+        // Formatting specifiers are applied when the value is rendered, see
+        // the `ToTokens` implementation.
+        f.write_str("{}")
     }
 }
 
@@ -42,6 +34,11 @@ impl<V: fmt::Display> fmt::Display for lower_ast::NodeToken<V> {
         match self {
             Self::Doctype => f.write_str("<!DOCTYPE html>"),
 
+            // Comments are enclosed in "<!--" and "-->", with the text written
+            // as a value
+            Self::CommentStart => f.write_str("<!--"),
+            Self::CommentEnd => f.write_str("-->"),
+
             // Opening tags start with '<'
             Self::OpeningTagStart => f.write_char('<'),
             // Opening tag name
@@ -71,9 +68,25 @@ impl<V: fmt::Display> fmt::Display for lower_ast::NodeToken<V> {
             Self::AttrValue(value) => value.fmt(f),
             // Ending quote
             Self::AttrValueEndQuote => f.write_char('"'),
+            // Boolean attribute, written by a formatting argument
+            Self::BoolAttr(..) => f.write_str("{}"),
+            // Optional attribute, written by a formatting argument
+            Self::OptionalAttr(..) => f.write_str("{}"),
+            // Class list, written by a formatting argument
+            Self::ClassList(..) => f.write_str("{}"),
+            // Spread attributes, written by a formatting argument
+            Self::Spread(..) => f.write_str("{}"),
+
+            // Component, written by a formatting argument
+            Self::Component(_) => f.write_str("{}"),
+            // Function call, written by a formatting argument
+            Self::Call(_) => f.write_str("{}"),
 
             // Stray value
             Self::Value(value) => value.fmt(f),
+
+            // Control flow is written by a formatting argument
+            Self::ControlFlow(_) => f.write_str("{}"),
         }
     }
 }
@@ -0,0 +1,837 @@
+//! Knowledge about standard HTML elements and attributes.
+
+/// Boolean attributes, which are either present or absent.
+///
+/// See <https://html.spec.whatwg.org/multipage/indices.html#attributes-3>.
+/// `hidden` is an enumerated attribute, but any value other than
+/// `until-found` hides the element, so it is written like a boolean
+/// attribute for expressions, while `hidden="until-found"` is a literal.
+pub(crate) const BOOLEAN_ATTRS: &[&str] = &[
+    "allowfullscreen",
+    "alpha",
+    "async",
+    "autofocus",
+    "autoplay",
+    "checked",
+    "controls",
+    "default",
+    "defer",
+    "disabled",
+    "formnovalidate",
+    "hidden",
+    "inert",
+    "ismap",
+    "itemscope",
+    "loop",
+    "multiple",
+    "muted",
+    "nomodule",
+    "novalidate",
+    "open",
+    "playsinline",
+    "readonly",
+    "required",
+    "reversed",
+    "selected",
+    "shadowrootclonable",
+    "shadowrootcustomelementregistry",
+    "shadowrootdelegatesfocus",
+    "shadowrootserializable",
+];
+
+/// Returns `true` if the attribute is a boolean attribute.
+pub(crate) fn is_boolean_attr(name: &str) -> bool {
+    BOOLEAN_ATTRS.contains(&name.to_ascii_lowercase().as_str())
+}
+
+/// Standard HTML elements, including void elements.
+///
+/// See <https://html.spec.whatwg.org/multipage/indices.html#elements-3>.
+pub(crate) const ELEMENTS: &[&str] = &[
+    "a",
+    "abbr",
+    "address",
+    "area",
+    "article",
+    "aside",
+    "audio",
+    "b",
+    "base",
+    "bdi",
+    "bdo",
+    "blockquote",
+    "body",
+    "br",
+    "button",
+    "canvas",
+    "caption",
+    "cite",
+    "code",
+    "col",
+    "colgroup",
+    "data",
+    "datalist",
+    "dd",
+    "del",
+    "details",
+    "dfn",
+    "dialog",
+    "div",
+    "dl",
+    "dt",
+    "em",
+    "embed",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "head",
+    "header",
+    "hgroup",
+    "hr",
+    "html",
+    "i",
+    "iframe",
+    "img",
+    "input",
+    "ins",
+    "kbd",
+    "label",
+    "legend",
+    "li",
+    "link",
+    "main",
+    "map",
+    "mark",
+    "menu",
+    "meta",
+    "meter",
+    "nav",
+    "noscript",
+    "object",
+    "ol",
+    "optgroup",
+    "option",
+    "output",
+    "p",
+    "picture",
+    "pre",
+    "progress",
+    "q",
+    "rp",
+    "rt",
+    "ruby",
+    "s",
+    "samp",
+    "script",
+    "search",
+    "section",
+    "select",
+    "slot",
+    "small",
+    "source",
+    "span",
+    "strong",
+    "style",
+    "sub",
+    "summary",
+    "sup",
+    "table",
+    "tbody",
+    "td",
+    "template",
+    "textarea",
+    "tfoot",
+    "th",
+    "thead",
+    "time",
+    "title",
+    "tr",
+    "track",
+    "u",
+    "ul",
+    "var",
+    "video",
+    "wbr",
+];
+
+/// Void elements, which have no content and no closing tag.
+///
+/// See <https://html.spec.whatwg.org/multipage/syntax.html#void-elements>.
+pub(crate) const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta",
+    "source", "track", "wbr",
+];
+
+/// Returns `true` if the element is a standard HTML element.
+pub(crate) fn is_element(name: &str) -> bool {
+    ELEMENTS.contains(&name.to_ascii_lowercase().as_str())
+}
+
+/// Returns `true` if the element is a void element.
+pub(crate) fn is_void_element(name: &str) -> bool {
+    VOID_ELEMENTS.contains(&name.to_ascii_lowercase().as_str())
+}
+
+/// Elements that can only be children of certain elements, and their
+/// permitted parents.
+///
+/// See the "contexts in which this element can be used" of each element in
+/// <https://html.spec.whatwg.org/multipage/>.
+const PERMITTED_PARENTS: &[(&str, &[&str])] = &[
+    ("caption", &["table"]),
+    ("col", &["colgroup"]),
+    ("colgroup", &["table"]),
+    ("dd", &["dl", "div"]),
+    ("dt", &["dl", "div"]),
+    ("figcaption", &["figure"]),
+    ("legend", &["fieldset"]),
+    ("li", &["ul", "ol", "menu"]),
+    ("optgroup", &["select"]),
+    ("option", &["select", "datalist", "optgroup"]),
+    ("rp", &["ruby"]),
+    ("rt", &["ruby"]),
+    ("summary", &["details"]),
+    ("tbody", &["table"]),
+    ("td", &["tr"]),
+    ("tfoot", &["table"]),
+    ("th", &["tr"]),
+    ("thead", &["table"]),
+    ("tr", &["thead", "tbody", "tfoot"]),
+];
+
+/// Elements that close an open `<p>` element when they start, i.e. flow
+/// content that is not phrasing content.
+///
+/// See <https://html.spec.whatwg.org/multipage/grouping-content.html#the-p-element>.
+const P_CLOSING_ELEMENTS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "details",
+    "dialog",
+    "div",
+    "dl",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hgroup",
+    "hr",
+    "main",
+    "menu",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "search",
+    "section",
+    "table",
+    "ul",
+];
+
+/// Elements that limit the scope in which an open `<p>` element is closed.
+///
+/// See <https://html.spec.whatwg.org/multipage/parsing.html#has-an-element-in-button-scope>.
+const SCOPE_BOUNDARIES: &[&str] = &[
+    "button", "caption", "html", "object", "table", "td", "template", "th",
+];
+
+/// Interactive content, which cannot be nested in `<a>` or `<button>`.
+///
+/// See <https://html.spec.whatwg.org/multipage/dom.html#interactive-content>.
+const INTERACTIVE_ELEMENTS: &[&str] = &[
+    "a", "button", "details", "embed", "iframe", "input", "label", "select",
+    "textarea",
+];
+
+/// Returns the permitted parents of the element, if it can only be a child
+/// of certain elements.
+pub(crate) fn permitted_parents(name: &str) -> Option<&'static [&'static str]> {
+    let name = name.to_ascii_lowercase();
+    PERMITTED_PARENTS
+        .iter()
+        .find(|(element, _)| *element == name)
+        .map(|(_, parents)| *parents)
+}
+
+/// Returns `true` if the element closes an open `<p>` element.
+pub(crate) fn closes_p(name: &str) -> bool {
+    P_CLOSING_ELEMENTS.contains(&name.to_ascii_lowercase().as_str())
+}
+
+/// Returns `true` if an open `<p>` element is not closed past the element.
+pub(crate) fn is_scope_boundary(name: &str) -> bool {
+    SCOPE_BOUNDARIES.contains(&name.to_ascii_lowercase().as_str())
+}
+
+/// Returns `true` if the element is interactive content. Hidden inputs are
+/// checked separately.
+pub(crate) fn is_interactive(name: &str) -> bool {
+    INTERACTIVE_ELEMENTS.contains(&name.to_ascii_lowercase().as_str())
+}
+
+/// Elements from other namespaces that can be embedded in HTML. Their
+/// descendants follow the SVG and MathML standards instead.
+const FOREIGN_ELEMENTS: &[&str] = &["math", "svg"];
+
+/// Attributes of standard HTML elements, excluding event handlers.
+///
+/// See <https://html.spec.whatwg.org/multipage/indices.html#attributes-3>.
+const ATTRS: &[&str] = &[
+    "abbr",
+    "accept",
+    "accept-charset",
+    "accesskey",
+    "action",
+    "allow",
+    "allowfullscreen",
+    "alpha",
+    "alt",
+    "as",
+    "async",
+    "autocapitalize",
+    "autocomplete",
+    "autocorrect",
+    "autofocus",
+    "autoplay",
+    "blocking",
+    "charset",
+    "checked",
+    "cite",
+    "class",
+    "closedby",
+    "color",
+    "colorspace",
+    "cols",
+    "colspan",
+    "command",
+    "commandfor",
+    "content",
+    "contenteditable",
+    "controls",
+    "coords",
+    "crossorigin",
+    "data",
+    "datetime",
+    "decoding",
+    "default",
+    "defer",
+    "dir",
+    "dirname",
+    "disabled",
+    "download",
+    "draggable",
+    "enctype",
+    "enterkeyhint",
+    "fetchpriority",
+    "for",
+    "form",
+    "formaction",
+    "formenctype",
+    "formmethod",
+    "formnovalidate",
+    "formtarget",
+    "headers",
+    "height",
+    "hidden",
+    "high",
+    "href",
+    "hreflang",
+    "http-equiv",
+    "id",
+    "imagesizes",
+    "imagesrcset",
+    "inert",
+    "inputmode",
+    "integrity",
+    "is",
+    "ismap",
+    "itemid",
+    "itemprop",
+    "itemref",
+    "itemscope",
+    "itemtype",
+    "kind",
+    "label",
+    "lang",
+    "list",
+    "loading",
+    "loop",
+    "low",
+    "max",
+    "maxlength",
+    "media",
+    "method",
+    "min",
+    "minlength",
+    "multiple",
+    "muted",
+    "name",
+    "nomodule",
+    "nonce",
+    "novalidate",
+    "open",
+    "optimum",
+    "pattern",
+    "ping",
+    "placeholder",
+    "playsinline",
+    "popover",
+    "popovertarget",
+    "popovertargetaction",
+    "poster",
+    "preload",
+    "readonly",
+    "referrerpolicy",
+    "rel",
+    "required",
+    "reversed",
+    // Defined by WAI-ARIA, like the `aria-*` attributes.
+    "role",
+    "rows",
+    "rowspan",
+    "sandbox",
+    "scope",
+    "selected",
+    "shadowrootclonable",
+    "shadowrootcustomelementregistry",
+    "shadowrootdelegatesfocus",
+    "shadowrootmode",
+    "shadowrootserializable",
+    "shape",
+    "size",
+    "sizes",
+    "slot",
+    "span",
+    "spellcheck",
+    "src",
+    "srcdoc",
+    "srclang",
+    "srcset",
+    "start",
+    "step",
+    "style",
+    "tabindex",
+    "target",
+    "title",
+    "translate",
+    "type",
+    "usemap",
+    "value",
+    "width",
+    "wrap",
+    "writingsuggestions",
+];
+
+/// Event handler attributes, including the handlers added to HTML elements by
+/// the CSS animations, CSS transitions, pointer events and touch events
+/// standards.
+///
+/// See <https://html.spec.whatwg.org/multipage/indices.html#ix-event-handlers>.
+const EVENT_HANDLER_ATTRS: &[&str] = &[
+    "onabort",
+    "onafterprint",
+    "onanimationcancel",
+    "onanimationend",
+    "onanimationiteration",
+    "onanimationstart",
+    "onauxclick",
+    "onbeforeinput",
+    "onbeforematch",
+    "onbeforeprint",
+    "onbeforetoggle",
+    "onbeforeunload",
+    "onblur",
+    "oncancel",
+    "oncanplay",
+    "oncanplaythrough",
+    "onchange",
+    "onclick",
+    "onclose",
+    "oncommand",
+    "oncontextlost",
+    "oncontextmenu",
+    "oncontextrestored",
+    "oncopy",
+    "oncuechange",
+    "oncut",
+    "ondblclick",
+    "ondrag",
+    "ondragend",
+    "ondragenter",
+    "ondragleave",
+    "ondragover",
+    "ondragstart",
+    "ondrop",
+    "ondurationchange",
+    "onemptied",
+    "onended",
+    "onerror",
+    "onfocus",
+    "onformdata",
+    "ongotpointercapture",
+    "onhashchange",
+    "oninput",
+    "oninvalid",
+    "onkeydown",
+    "onkeypress",
+    "onkeyup",
+    "onlanguagechange",
+    "onload",
+    "onloadeddata",
+    "onloadedmetadata",
+    "onloadstart",
+    "onlostpointercapture",
+    "onmessage",
+    "onmessageerror",
+    "onmousedown",
+    "onmouseenter",
+    "onmouseleave",
+    "onmousemove",
+    "onmouseout",
+    "onmouseover",
+    "onmouseup",
+    "onoffline",
+    "ononline",
+    "onpagehide",
+    "onpagereveal",
+    "onpageshow",
+    "onpageswap",
+    "onpaste",
+    "onpause",
+    "onplay",
+    "onplaying",
+    "onpointercancel",
+    "onpointerdown",
+    "onpointerenter",
+    "onpointerleave",
+    "onpointermove",
+    "onpointerout",
+    "onpointerover",
+    "onpointerup",
+    "onpopstate",
+    "onprogress",
+    "onratechange",
+    "onrejectionhandled",
+    "onreset",
+    "onresize",
+    "onscroll",
+    "onscrollend",
+    "onsecuritypolicyviolation",
+    "onseeked",
+    "onseeking",
+    "onselect",
+    "onselectionchange",
+    "onselectstart",
+    "onslotchange",
+    "onstalled",
+    "onstorage",
+    "onsubmit",
+    "onsuspend",
+    "ontimeupdate",
+    "ontoggle",
+    "ontouchcancel",
+    "ontouchend",
+    "ontouchmove",
+    "ontouchstart",
+    "ontransitioncancel",
+    "ontransitionend",
+    "ontransitionrun",
+    "ontransitionstart",
+    "onunhandledrejection",
+    "onunload",
+    "onvolumechange",
+    "onwaiting",
+    "onwheel",
+];
+
+/// Non-abstract WAI-ARIA roles, which can be used in the `role` attribute.
+///
+/// See <https://www.w3.org/TR/wai-aria-1.2/#role_definitions>.
+const ARIA_ROLES: &[&str] = &[
+    "alert",
+    "alertdialog",
+    "application",
+    "article",
+    "banner",
+    "blockquote",
+    "button",
+    "caption",
+    "cell",
+    "checkbox",
+    "code",
+    "columnheader",
+    "combobox",
+    "complementary",
+    "contentinfo",
+    "definition",
+    "deletion",
+    "dialog",
+    "document",
+    "emphasis",
+    "feed",
+    "figure",
+    "form",
+    "generic",
+    "grid",
+    "gridcell",
+    "group",
+    "heading",
+    "img",
+    "insertion",
+    "link",
+    "list",
+    "listbox",
+    "listitem",
+    "log",
+    "main",
+    "marquee",
+    "math",
+    "menu",
+    "menubar",
+    "menuitem",
+    "menuitemcheckbox",
+    "menuitemradio",
+    "meter",
+    "navigation",
+    "none",
+    "note",
+    "option",
+    "paragraph",
+    "presentation",
+    "progressbar",
+    "radio",
+    "radiogroup",
+    "region",
+    "row",
+    "rowgroup",
+    "rowheader",
+    "scrollbar",
+    "search",
+    "searchbox",
+    "separator",
+    "slider",
+    "spinbutton",
+    "status",
+    "strong",
+    "subscript",
+    "superscript",
+    "switch",
+    "tab",
+    "table",
+    "tablist",
+    "tabpanel",
+    "term",
+    "textbox",
+    "time",
+    "timer",
+    "toolbar",
+    "tooltip",
+    "tree",
+    "treegrid",
+    "treeitem",
+];
+
+/// WAI-ARIA states and properties, the `aria-*` attributes.
+///
+/// See <https://www.w3.org/TR/wai-aria-1.2/#state_prop_def>.
+const ARIA_ATTRS: &[&str] = &[
+    "aria-activedescendant",
+    "aria-atomic",
+    "aria-autocomplete",
+    "aria-braillelabel",
+    "aria-brailleroledescription",
+    "aria-busy",
+    "aria-checked",
+    "aria-colcount",
+    "aria-colindex",
+    "aria-colindextext",
+    "aria-colspan",
+    "aria-controls",
+    "aria-current",
+    "aria-describedby",
+    "aria-description",
+    "aria-details",
+    "aria-disabled",
+    "aria-dropeffect",
+    "aria-errormessage",
+    "aria-expanded",
+    "aria-flowto",
+    "aria-grabbed",
+    "aria-haspopup",
+    "aria-hidden",
+    "aria-invalid",
+    "aria-keyshortcuts",
+    "aria-label",
+    "aria-labelledby",
+    "aria-level",
+    "aria-live",
+    "aria-modal",
+    "aria-multiline",
+    "aria-multiselectable",
+    "aria-orientation",
+    "aria-owns",
+    "aria-placeholder",
+    "aria-posinset",
+    "aria-pressed",
+    "aria-readonly",
+    "aria-relevant",
+    "aria-required",
+    "aria-roledescription",
+    "aria-rowcount",
+    "aria-rowindex",
+    "aria-rowindextext",
+    "aria-rowspan",
+    "aria-selected",
+    "aria-setsize",
+    "aria-sort",
+    "aria-valuemax",
+    "aria-valuemin",
+    "aria-valuenow",
+    "aria-valuetext",
+];
+
+/// Returns `true` if the element is from another namespace, i.e. `<svg>` or
+/// `<math>`.
+pub(crate) fn is_foreign_element(name: &str) -> bool {
+    FOREIGN_ELEMENTS.contains(&name.to_ascii_lowercase().as_str())
+}
+
+/// Returns `true` if the element is a custom element, whose name must
+/// contain a dash.
+pub(crate) fn is_custom_element(name: &str) -> bool {
+    name.contains('-')
+}
+
+/// Returns `true` if the attribute is defined for HTML elements, including
+/// `data-*` and `aria-*` attributes.
+pub(crate) fn is_attr(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    name.starts_with("data-")
+        || name.starts_with("aria-")
+        || ATTRS.contains(&name.as_str())
+        || EVENT_HANDLER_ATTRS.contains(&name.as_str())
+}
+
+/// Returns the standard element with the name closest to `name`, if any is
+/// close enough to be a likely typo.
+pub(crate) fn suggest_element(name: &str) -> Option<&'static str> {
+    closest(name, ELEMENTS.iter().chain(FOREIGN_ELEMENTS))
+}
+
+/// Returns the attribute with the name closest to `name`, if any is close
+/// enough to be a likely typo.
+pub(crate) fn suggest_attr(name: &str) -> Option<&'static str> {
+    closest(name, ATTRS.iter().chain(EVENT_HANDLER_ATTRS))
+}
+
+/// Returns `true` if the role is a non-abstract WAI-ARIA role.
+pub(crate) fn is_aria_role(role: &str) -> bool {
+    ARIA_ROLES.contains(&role.to_ascii_lowercase().as_str())
+}
+
+/// Returns `true` if the attribute is a WAI-ARIA state or property.
+pub(crate) fn is_aria_attr(name: &str) -> bool {
+    ARIA_ATTRS.contains(&name.to_ascii_lowercase().as_str())
+}
+
+/// Returns the WAI-ARIA role closest to `role`, if any is close enough to be
+/// a likely typo.
+pub(crate) fn suggest_aria_role(role: &str) -> Option<&'static str> {
+    closest(role, ARIA_ROLES.iter())
+}
+
+/// Returns the WAI-ARIA state or property closest to `name`, if any is close
+/// enough to be a likely typo.
+pub(crate) fn suggest_aria_attr(name: &str) -> Option<&'static str> {
+    closest(name, ARIA_ATTRS.iter())
+}
+
+fn closest<'a>(
+    name: &str,
+    candidates: impl Iterator<Item = &'a &'static str>,
+) -> Option<&'static str> {
+    let name = name.to_ascii_lowercase();
+    // Allows one edit for every three characters, like rustc's suggestions.
+    let max = name.len().max(3) / 3;
+    candidates
+        .map(|candidate| (edit_distance(&name, candidate), *candidate))
+        .filter(|(distance, _)| *distance <= max)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Returns the number of insertions, deletions, substitutions and
+/// transpositions of adjacent characters needed to turn `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+
+    // `d[i][j]` is the distance between `a[..i]` and `b[..j]`.
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in d[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+
+    d[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edit_distances() {
+        assert_eq!(edit_distance("div", "div"), 0);
+        assert_eq!(edit_distance("dvi", "div"), 1);
+        assert_eq!(edit_distance("clas", "class"), 1);
+        assert_eq!(edit_distance("spam", "span"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn suggestions() {
+        assert_eq!(suggest_element("dvi"), Some("div"));
+        assert_eq!(suggest_element("Sapn"), Some("span"));
+        assert_eq!(suggest_element("foo"), None);
+        assert_eq!(suggest_attr("clas"), Some("class"));
+        assert_eq!(suggest_attr("onclik"), Some("onclick"));
+        assert_eq!(suggest_attr("placeholdr"), Some("placeholder"));
+        assert_eq!(suggest_attr("xyzzy"), None);
+        assert_eq!(suggest_aria_role("buton"), Some("button"));
+        assert_eq!(suggest_aria_attr("aria-lable"), Some("aria-label"));
+    }
+}
@@ -0,0 +1,466 @@
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::{env, fs};
+
+use proc_macro2::{Delimiter, Group, Span, TokenStream, TokenTree};
+use quote::quote;
+use syn::parse::Parser as _;
+
+use crate::analyze::analyze_template;
+use crate::options::Options;
+use crate::parse::parse_unresolved;
+use crate::{ast, layout, lower_ast, IncludeInput};
+
+/// Expands `fhtml::include!`, reading the template from a file relative to
+/// `CARGO_MANIFEST_DIR`.
+pub(crate) fn include(input: IncludeInput) -> syn::Result<TokenStream> {
+    let IncludeInput { path, bindings } = input;
+
+    let nodes = with_files(path.span(), |files| {
+        let (full_path, options, template) = files.parse(&path)?;
+        let mut nodes = vec![track(&full_path)];
+        nodes.extend(layout::resolve(template, files)?);
+        analyze_template(&nodes, &options)?;
+        Ok(nodes)
+    })?;
+
+    // The bindings are in scope for the whole template, like `@let`.
+    let mut all_nodes = bindings
+        .into_iter()
+        .map(|(name, expr)| {
+            let local = syn::Local {
+                attrs: vec![],
+                let_token: Default::default(),
+                pat: syn::Pat::Ident(syn::PatIdent {
+                    attrs: vec![],
+                    by_ref: None,
+                    mutability: None,
+                    ident: name,
+                    subpat: None,
+                }),
+                init: Some(syn::LocalInit {
+                    eq_token: Default::default(),
+                    expr: Box::new(expr),
+                    diverge: None,
+                }),
+                semi_token: Default::default(),
+            };
+            ast::Node::ControlFlow(Box::new(ast::ControlFlow::Let(ast::Let {
+                local,
+            })))
+        })
+        .collect::<Vec<_>>();
+    all_nodes.extend(nodes);
+
+    let (fmt, args) =
+        lower_ast::lower_format_args(all_nodes, lower_ast::Uses::Many);
+
+    Ok(quote! {
+        ::core::format_args!(#fmt, #(#args),*)
+    })
+}
+
+/// The template files read while expanding a macro, whose errors are reported
+/// with their line and column in the file, see `with_files`.
+#[derive(Default)]
+pub(crate) struct Files {
+    /// The name and source of every file.
+    files: Vec<(String, String)>,
+    /// The file and byte offset of every token, numbered from 1 in the order
+    /// they were read, where the offset is `None` if it wasn't found.
+    tokens: Vec<(usize, Option<usize>)>,
+    /// While locating errors, the tokens whose number has a bit of the mask
+    /// set are given the span.
+    marking: Option<(usize, Span)>,
+}
+
+impl Files {
+    /// Reads and parses the template file at `path`, relative to
+    /// `CARGO_MANIFEST_DIR`, returning its full path, its options and the
+    /// template.
+    ///
+    /// Errors reading the file are reported at the span of `path`.
+    pub(crate) fn parse(
+        &mut self,
+        path: &syn::LitStr,
+    ) -> syn::Result<(PathBuf, Options, ast::Template<ast::ArgValue>)> {
+        let manifest_dir = env::var("CARGO_MANIFEST_DIR").map_err(|_| {
+            syn::Error::new(path.span(), "`CARGO_MANIFEST_DIR` is not set")
+        })?;
+        let full_path = Path::new(&manifest_dir).join(path.value());
+        let source = fs::read_to_string(&full_path).map_err(|err| {
+            syn::Error::new(
+                path.span(),
+                format_args!(
+                    "couldn't read `{}`: {}",
+                    full_path.display(),
+                    err
+                ),
+            )
+        })?;
+
+        let name = path.value();
+        let tokens = TokenStream::from_str(&source).map_err(|err| {
+            let message = match lex_error(&source) {
+                Some(offset) => {
+                    let (line, column) = line_column(&source, offset);
+                    format!("{}:{}:{}: {}", name, line, column, err)
+                }
+                None => format!("{}: {}", name, err),
+            };
+            syn::Error::new(path.span(), message)
+        })?;
+
+        let file = self.files.len();
+        let marking = self.marking;
+        let numbered = &mut self.tokens;
+        let tokens = respan(tokens, &source, &mut Some(0), &mut |offset| {
+            numbered.push((file, offset));
+            match marking {
+                Some((mask, span)) if numbered.len() & mask != 0 => span,
+                _ => Span::call_site(),
+            }
+        });
+        self.files.push((name, source));
+
+        let (options, template) = parse_unresolved.parse2(tokens)?;
+        Ok((full_path, options, template))
+    }
+}
+
+/// Runs `f`, which reads template files through `files`, reporting the errors
+/// in the files with their line and column at `span`.
+///
+/// Tokens parsed from a string all have the span of the macro invocation, so
+/// an error doesn't tell which token it is at. To find out, `f` is run again
+/// for every bit of the token numbers, giving `span` to the tokens whose
+/// number has the bit set, and the number of the token of each error is read
+/// from the runs that gave it `span`. A first run with no token marked tells
+/// apart the errors that are at `span` anyway.
+pub(crate) fn with_files<T>(
+    span: Span,
+    f: impl Fn(&mut Files) -> syn::Result<T>,
+) -> syn::Result<T> {
+    let mut files = Files::default();
+    let err = match f(&mut files) {
+        Ok(value) => return Ok(value),
+        Err(err) => err,
+    };
+    let text = match span.source_text() {
+        Some(text) if !files.tokens.is_empty() => text,
+        _ => return Err(err),
+    };
+
+    // Returns whether each error is at `span` with the tokens in `mask`
+    // marked, if the run gives as many errors as the first one.
+    let errors = err.clone().into_iter().collect::<Vec<_>>();
+    let marked = |mask: usize| {
+        let mut files = Files {
+            marking: Some((mask, span)),
+            ..Files::default()
+        };
+        let marked = f(&mut files)
+            .err()?
+            .into_iter()
+            .map(|err| err.span().source_text().as_ref() == Some(&text))
+            .collect::<Vec<_>>();
+        (marked.len() == errors.len()).then_some(marked)
+    };
+
+    let mut numbers = match marked(0) {
+        Some(marked) => marked
+            .into_iter()
+            .map(|marked| (!marked).then_some(0))
+            .collect::<Vec<_>>(),
+        None => return Err(err),
+    };
+    for bit in 0..usize::BITS - files.tokens.len().leading_zeros() {
+        let marked = match marked(1 << bit) {
+            Some(marked) => marked,
+            None => return Err(err),
+        };
+        for (number, marked) in numbers.iter_mut().zip(marked) {
+            if let Some(number) = number {
+                *number |= usize::from(marked) << bit;
+            }
+        }
+    }
+
+    // Only the offsets of the tokens with errors are converted to a line and
+    // column.
+    let located = errors.into_iter().zip(numbers).map(|(err, number)| {
+        let token = number
+            .and_then(|number| number.checked_sub(1))
+            .and_then(|index| files.tokens.get(index));
+        let (name, source, offset) = match token {
+            Some(&(file, offset)) => {
+                let (name, source) = &files.files[file];
+                (name, source, offset)
+            }
+            None => return err,
+        };
+        let message = match offset {
+            Some(offset) => {
+                let (line, column) = line_column(source, offset);
+                format!("{}:{}:{}: {}", name, line, column, err)
+            }
+            None => format!("{}: {}", name, err),
+        };
+        syn::Error::new(span, message)
+    });
+    Err(located
+        .reduce(|mut errors, err| {
+            errors.combine(err);
+            errors
+        })
+        .unwrap_or(err))
+}
+
+/// Returns a node that renders nothing, but makes the compiler rebuild when
+/// the file at `path` changes, by including it as bytes.
+pub(crate) fn track(path: &Path) -> ast::Node<ast::ArgValue> {
+    let path = path.to_string_lossy();
+    let value = syn::parse_quote! {
+        {
+            const _: &[u8] = ::core::include_bytes!(#path);
+            ""
+        }
+    };
+
+    ast::Node::Value(ast::ArgValue::Expr { value, specs: None })
+}
+
+/// Gives every token the span that `f` returns for the byte offset of the
+/// token in `source`, visiting the tokens depth-first, where a group is at its
+/// opening delimiter.
+///
+/// The offset of a token is found by skipping its text after `offset`, so it
+/// is `None` from the first token whose text isn't in the source, e.g. a doc
+/// comment, which is lexed as an attribute.
+fn respan(
+    tokens: TokenStream,
+    source: &str,
+    offset: &mut Option<usize>,
+    f: &mut dyn FnMut(Option<usize>) -> Span,
+) -> TokenStream {
+    tokens
+        .into_iter()
+        .map(|token| match token {
+            TokenTree::Group(group) => {
+                let (open, close) = match group.delimiter() {
+                    Delimiter::Parenthesis => ("(", ")"),
+                    Delimiter::Brace => ("{", "}"),
+                    Delimiter::Bracket => ("[", "]"),
+                    Delimiter::None => ("", ""),
+                };
+                let span = f(skip(source, offset, open));
+                let stream = respan(group.stream(), source, offset, f);
+                skip(source, offset, close);
+
+                let mut group = Group::new(group.delimiter(), stream);
+                group.set_span(span);
+                TokenTree::Group(group)
+            }
+            mut token => {
+                let text = match &token {
+                    TokenTree::Punct(punct) => punct.as_char().to_string(),
+                    token => token.to_string(),
+                };
+                token.set_span(f(skip(source, offset, &text)));
+                token
+            }
+        })
+        .collect()
+}
+
+/// Skips `text` after any whitespace and comments at `offset`, returning its
+/// offset.
+fn skip(source: &str, offset: &mut Option<usize>, text: &str) -> Option<usize> {
+    let start = offset
+        .and_then(|offset| skip_trivia(source, offset).ok())
+        .filter(|&start| source[start..].starts_with(text));
+    *offset = start.map(|start| start + text.len());
+    start
+}
+
+/// Returns the offset after the whitespace and comments at `offset`, or the
+/// offset of an unterminated block comment.
+fn skip_trivia(source: &str, mut offset: usize) -> Result<usize, usize> {
+    loop {
+        let rest = &source[offset..];
+        let trimmed = rest.trim_start();
+        offset += rest.len() - trimmed.len();
+
+        if trimmed.starts_with("//") {
+            offset += trimmed.find('\n').unwrap_or(trimmed.len());
+        } else if trimmed.starts_with("/*") {
+            // Block comments nest.
+            let mut depth = 0;
+            let mut len = 0;
+            loop {
+                let rest = &trimmed[len..];
+                if rest.starts_with("/*") {
+                    depth += 1;
+                    len += 2;
+                } else if rest.starts_with("*/") {
+                    depth -= 1;
+                    len += 2;
+                    if depth == 0 {
+                        break;
+                    }
+                } else {
+                    match rest.chars().next() {
+                        Some(c) => len += c.len_utf8(),
+                        None => return Err(offset),
+                    }
+                }
+            }
+            offset += len;
+        } else {
+            return Ok(offset);
+        }
+    }
+}
+
+/// Returns the byte offset of the token that can't be lexed, i.e. an
+/// unterminated string or comment, or an unbalanced delimiter.
+fn lex_error(source: &str) -> Option<usize> {
+    let mut offset = 0;
+    let mut delimiters = Vec::new();
+    loop {
+        offset = match skip_trivia(source, offset) {
+            Ok(offset) => offset,
+            Err(offset) => return Some(offset),
+        };
+        let rest = &source[offset..];
+        let c = match rest.chars().next() {
+            Some(c) => c,
+            None => return delimiters.pop(),
+        };
+
+        // The prefix of a string literal, e.g. `b` or `r#`.
+        let prefix = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '#'))
+            .unwrap_or(rest.len());
+        let raw_hashes = rest[..prefix]
+            .strip_prefix(['b', 'c'])
+            .unwrap_or(&rest[..prefix])
+            .strip_prefix('r')
+            .filter(|hashes| hashes.chars().all(|c| c == '#'));
+        if rest[prefix..].starts_with('"')
+            && (raw_hashes.is_some()
+                || matches!(&rest[..prefix], "" | "b" | "c"))
+        {
+            let body = &rest[prefix + 1..];
+            let end = match raw_hashes {
+                Some(hashes) => body
+                    .find(&format!("\"{}", hashes))
+                    .map(|i| i + 1 + hashes.len()),
+                None => {
+                    let mut escaped = false;
+                    body.find(|c| {
+                        let end = c == '"' && !escaped;
+                        escaped = c == '\\' && !escaped;
+                        end
+                    })
+                    .map(|i| i + 1)
+                }
+            };
+            match end {
+                Some(end) => offset += prefix + 1 + end,
+                None => return Some(offset),
+            }
+            continue;
+        }
+
+        if c == '\'' {
+            // A character literal, or the start of a lifetime.
+            let len = match rest[1..].chars().next() {
+                Some('\\') => {
+                    rest.get(3..).and_then(|r| r.find('\'')).map(|i| i + 4)
+                }
+                Some(c) if rest[1 + c.len_utf8()..].starts_with('\'') => {
+                    Some(2 + c.len_utf8())
+                }
+                _ => None,
+            };
+            offset += len.unwrap_or(1);
+            continue;
+        }
+
+        match c {
+            '(' | '[' | '{' => delimiters.push(offset),
+            ')' | ']' | '}' => {
+                let open = delimiters.pop().map(|i| source.as_bytes()[i]);
+                let expected = match c {
+                    ')' => b'(',
+                    ']' => b'[',
+                    _ => b'{',
+                };
+                if open != Some(expected) {
+                    return Some(offset);
+                }
+            }
+            _ => {}
+        }
+        offset += prefix.max(c.len_utf8());
+    }
+}
+
+/// Returns the line and column of a byte offset, both starting at 1, where
+/// the column counts characters.
+fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_template;
+
+    /// Returns the errors of a template, whose tokens have a source text like
+    /// the input of a macro.
+    fn errors(source: &str) -> Vec<String> {
+        let tokens = TokenStream::from_str(source).unwrap();
+        match parse_template.parse2(tokens) {
+            Ok(_) => vec![],
+            Err(err) => err.into_iter().map(|err| err.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn error_locations() {
+        assert_eq!(
+            errors(r#"@extends "../testdata/errors/tags.fhtml""#),
+            ["../testdata/errors/tags.fhtml:2:17: closing tag mismatch, \
+              expected </p>, found </span>"]
+        );
+
+        let errors = errors(r#"@extends "../testdata/errors/comments.fhtml""#);
+        assert_eq!(errors.len(), 2);
+        assert!(
+            errors[0].starts_with("../testdata/errors/comments.fhtml:2:17: ")
+        );
+        assert!(
+            errors[1].starts_with("../testdata/errors/comments.fhtml:3:16: ")
+        );
+    }
+
+    #[test]
+    fn lex_error_locations() {
+        assert!(errors(r#"@extends "../testdata/errors/lex.fhtml""#)[0]
+            .starts_with("../testdata/errors/lex.fhtml:2:1: "));
+
+        let source = "<p>{'}'}</p>\n{ (a] }";
+        assert_eq!(
+            lex_error(source).map(|offset| line_column(source, offset)),
+            Some((2, 5))
+        );
+        assert_eq!(lex_error("/* a\n /* b */"), Some(0));
+    }
+}
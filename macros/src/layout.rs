@@ -0,0 +1,216 @@
+use std::path::PathBuf;
+
+use crate::analyze::Errors;
+use crate::ast;
+use crate::include::{track, Files};
+
+/// Resolves a template extending a layout into the nodes of the layout, with
+/// its blocks replaced by the blocks of the template.
+///
+/// Layouts can extend other layouts, in which case the blocks of the most
+/// derived template take precedence. Templates without `@extends` are
+/// returned as is. The layouts are read through `files`, and only their
+/// `minify` option applies, since the resolved nodes are analyzed with the
+/// options of the template.
+pub(crate) fn resolve(
+    template: ast::Template<ast::ArgValue>,
+    files: &mut Files,
+) -> syn::Result<Vec<ast::Node<ast::ArgValue>>> {
+    let mut extends = match template {
+        ast::Template::Nodes(nodes) => return Ok(nodes),
+        ast::Template::Extends(extends) => extends,
+    };
+
+    let mut overrides = Vec::<Override<_>>::new();
+    let mut visited = Vec::<PathBuf>::new();
+    let mut nodes = Vec::new();
+
+    let layout = loop {
+        for node in extends.blocks {
+            if let ast::Node::Block(block) = node {
+                if !overrides.iter().any(|o| o.block.name == block.name) {
+                    overrides.push(Override::new(block));
+                }
+            }
+        }
+
+        let (path, _, template) = files.parse(&extends.path)?;
+        if visited.contains(&path) {
+            return Err(syn::Error::new(
+                extends.path.span(),
+                format_args!(
+                    "layout `{}` extends itself",
+                    extends.path.value()
+                ),
+            ));
+        }
+        nodes.push(track(&path));
+        visited.push(path);
+
+        match template {
+            ast::Template::Nodes(nodes) => break nodes,
+            ast::Template::Extends(parent) => extends = parent,
+        }
+    };
+
+    nodes.extend(replace_blocks(layout, &mut overrides));
+
+    let mut errors = Errors::default();
+    for unused in overrides.iter().filter(|o| !o.used) {
+        errors.push(syn::Error::new(
+            unused.block.name.span(),
+            format_args!(
+                "block `{}` is not defined by the layout",
+                unused.block.name
+            ),
+        ));
+    }
+    errors.finish()?;
+
+    Ok(nodes)
+}
+
+/// A block overriding the block with the same name in a layout.
+struct Override<V> {
+    block: ast::Block<V>,
+    used: bool,
+    /// Whether the block is being expanded, in which case it can't replace
+    /// a block within its own nodes.
+    expanding: bool,
+}
+
+impl<V> Override<V> {
+    fn new(block: ast::Block<V>) -> Self {
+        Self {
+            block,
+            used: false,
+            expanding: false,
+        }
+    }
+}
+
+/// Replaces the nodes of every block that has an override, including blocks
+/// defined by the nodes of other overrides.
+fn replace_blocks<V: Clone>(
+    mut nodes: Vec<ast::Node<V>>,
+    overrides: &mut [Override<V>],
+) -> Vec<ast::Node<V>> {
+    for node in &mut nodes {
+        if let ast::Node::Block(block) = node {
+            let index = overrides
+                .iter()
+                .position(|o| o.block.name == block.name && !o.expanding);
+            if let Some(index) = index {
+                let replacement = overrides[index].block.nodes.clone();
+                overrides[index].used = true;
+                overrides[index].expanding = true;
+                block.nodes = replace_blocks(replacement, overrides);
+                overrides[index].expanding = false;
+                continue;
+            }
+        }
+
+        for branch in node.branches_mut() {
+            *branch = replace_blocks(std::mem::take(branch), overrides);
+        }
+    }
+
+    nodes
+}
+
+#[cfg(test)]
+mod tests {
+    use syn::parse::Parser as _;
+
+    use super::*;
+    use crate::lower_ast::{lower_format_args, Uses};
+    use crate::parse::parse_unresolved;
+
+    fn parse_layout(
+        tokens: proc_macro2::TokenStream,
+    ) -> Vec<ast::Node<ast::ArgValue>> {
+        match parse_unresolved.parse2(tokens).unwrap().1 {
+            ast::Template::Nodes(nodes) => nodes,
+            ast::Template::Extends(_) => panic!("expected nodes"),
+        }
+    }
+
+    fn parse_overrides(
+        tokens: proc_macro2::TokenStream,
+    ) -> Vec<Override<ast::ArgValue>> {
+        parse_layout(tokens)
+            .into_iter()
+            .map(|node| match node {
+                ast::Node::Block(block) => Override::new(block),
+                _ => panic!("expected a block"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn replaces_blocks() {
+        let layout = parse_layout(quote::quote! {
+            <title>@block title { "Default" }</title>
+            <main>@block content {}</main>
+        });
+        let mut overrides = parse_overrides(quote::quote! {
+            @block content { <p>"Hello"</p> @block footer { "Footer" } }
+        });
+        overrides.extend(parse_overrides(quote::quote! {
+            @block footer { "Custom" @block content { "Nested" } }
+        }));
+
+        let expected = parse_layout(quote::quote! {
+            <title>"Default"</title>
+            <main><p>"Hello"</p>"Custom""Nested"</main>
+        });
+        let lower = |nodes| {
+            let (fmt, args) = lower_format_args(nodes, Uses::Many);
+            (
+                fmt,
+                args.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            )
+        };
+        assert_eq!(
+            lower(replace_blocks(layout, &mut overrides)),
+            lower(expected)
+        );
+        assert!(overrides.iter().all(|o| o.used));
+    }
+
+    #[test]
+    fn unused_blocks() {
+        let layout = parse_layout(quote::quote!(@block content {}));
+        let mut overrides = parse_overrides(quote::quote! {
+            @block content {}
+            @block sidebar {}
+        });
+        replace_blocks(layout, &mut overrides);
+        assert!(overrides[0].used);
+        assert!(!overrides[1].used);
+    }
+
+    #[test]
+    fn analyzes_resolved_nodes() {
+        let check = |tokens| crate::parse::parse_template.parse2(tokens);
+
+        check(quote::quote! {
+            #![fhtml(strict)]
+            @extends "../testdata/layouts/list.fhtml"
+            @block items { <li>"a"</li> }
+        })
+        .expect("a block should be checked in the context of the layout");
+        check(quote::quote! {
+            #![fhtml(strict)]
+            @extends "../testdata/layouts/form.fhtml"
+            @block field { <li>"a"</li> }
+        })
+        .expect_err("a block should be checked in the context of the layout");
+        check(quote::quote! {
+            #![fhtml(a11y)]
+            @extends "../testdata/layouts/form.fhtml"
+            @block field { <input id="q" type="text"> }
+        })
+        .expect("labels of the layout should label the controls of a block");
+    }
+}
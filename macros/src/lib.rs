@@ -3,13 +3,34 @@ use quote::quote;
 
 mod analyze;
 mod ast;
+mod derive;
 mod fmt;
+mod html;
+mod include;
+mod layout;
 mod lower_ast;
+mod options;
 mod parse;
 
+/// Like `syn::parse_macro_input!`, for macros expanding to an expression.
+macro_rules! parse_expr_input {
+    ($input:ident as $ty:ty) => {
+        match syn::parse::<$ty>($input) {
+            Ok(input) => input,
+            Err(err) => return expr_error(err).into(),
+        }
+    };
+}
+
+/// Expands to the errors in a block, since only the first `compile_error!` of
+/// an expression would be reported otherwise.
+fn expr_error(err: syn::Error) -> proc_macro2::TokenStream {
+    let errors = err.into_compile_error();
+    quote!({ #errors })
+}
+
 pub(crate) struct FormatArgsInput {
-    pub fmt: String,
-    pub args: Vec<ast::ArgValue>,
+    pub nodes: Vec<ast::Node<ast::ArgValue>>,
 }
 
 /// A low level macro for creating an [`fmt::Arguments`] with formatted HTML.
@@ -20,11 +41,12 @@ pub(crate) struct FormatArgsInput {
 /// [`std::format_args!`]: https://doc.rust-lang.org/stable/std/macro.format_args.html
 #[proc_macro]
 pub fn format_args(input: TokenStream) -> TokenStream {
-    let FormatArgsInput { fmt, args } =
-        syn::parse_macro_input!(input as FormatArgsInput);
+    let FormatArgsInput { nodes } = parse_expr_input!(input as FormatArgsInput);
+    let (fmt, args) =
+        lower_ast::lower_format_args(nodes, lower_ast::Uses::Many);
 
     let output = quote! {
-        ::std::format_args!(#fmt, #(#args),*)
+        ::core::format_args!(#fmt, #(#args),*)
     };
 
     output.into()
@@ -38,13 +60,225 @@ pub fn format_args(input: TokenStream) -> TokenStream {
 /// [`std::format_args_nl!`]: https://doc.rust-lang.org/stable/std/macro.format_args_nl.html
 #[proc_macro]
 pub fn format_args_nl(input: TokenStream) -> TokenStream {
-    let FormatArgsInput { fmt, args } =
-        syn::parse_macro_input!(input as FormatArgsInput);
+    let FormatArgsInput { nodes } = parse_expr_input!(input as FormatArgsInput);
+    let (fmt, args) =
+        lower_ast::lower_format_args(nodes, lower_ast::Uses::Many);
 
     let fmt_with_nl = format!("{}<br>", fmt);
 
     let output = quote! {
-        ::std::format_args!(#fmt_with_nl, #(#args),*)
+        ::core::format_args!(#fmt_with_nl, #(#args),*)
+    };
+
+    output.into()
+}
+
+#[cfg(feature = "std")]
+pub(crate) struct WriteIoInput {
+    pub dst: syn::Expr,
+    pub segments: Vec<lower_ast::Segment>,
+}
+
+/// Writes formatted HTML to an [`io::Write`], returning an `io::Result<()>`.
+///
+/// Static markup is written as byte slices with [`write_all`], and embedded
+/// expressions are written with [`write_fmt`]. Each segment is a separate
+/// call, so files and sockets should be wrapped in a [`BufWriter`].
+///
+/// Like with `fhtml::write!`, `?` and `return` in embedded expressions apply
+/// to the enclosing function, while `?` is not supported within control flow,
+/// which is written by closures.
+///
+/// Requires the `std` feature.
+///
+/// [`io::Write`]: https://doc.rust-lang.org/stable/std/io/trait.Write.html
+/// [`write_all`]: https://doc.rust-lang.org/stable/std/io/trait.Write.html#method.write_all
+/// [`write_fmt`]: https://doc.rust-lang.org/stable/std/io/trait.Write.html#method.write_fmt
+/// [`BufWriter`]: https://doc.rust-lang.org/stable/std/io/struct.BufWriter.html
+#[cfg(feature = "std")]
+#[proc_macro]
+pub fn write_io(input: TokenStream) -> TokenStream {
+    let WriteIoInput { dst, segments } =
+        parse_expr_input!(input as WriteIoInput);
+
+    // The writes are in a labeled block rather than a closure, so that the
+    // embedded expressions are evaluated in the enclosing function.
+    let label =
+        syn::Lifetime::new("'__fhtml_write", proc_macro2::Span::mixed_site());
+    let err = syn::Ident::new("err", proc_macro2::Span::mixed_site());
+    let writes = segments.into_iter().map(|segment| {
+        let write = match segment {
+            lower_ast::Segment::Static(s) => {
+                let bytes = proc_macro2::Literal::byte_string(s.as_bytes());
+                quote! {
+                    ::std::io::Write::write_all(__fhtml_dst, #bytes)
+                }
+            }
+            lower_ast::Segment::Arg(arg) => quote! {
+                ::std::io::Write::write_fmt(
+                    __fhtml_dst,
+                    ::core::format_args!("{}", #arg),
+                )
+            },
+        };
+        quote! {
+            if let ::core::result::Result::Err(#err) = #write {
+                break #label ::core::result::Result::Err(#err);
+            }
+        }
+    });
+
+    let output = quote! {{
+        use ::fhtml::__private::WriteIo as _;
+        // Temporaries in `dst` live until the end of the `match`.
+        match (#dst).as_io_write() {
+            __fhtml_dst => #label: {
+                #(#writes)*
+                ::std::io::Result::Ok(())
+            },
+        }
+    }};
+
+    output.into()
+}
+
+#[cfg(feature = "tokio")]
+pub(crate) struct WriteAsyncInput {
+    pub dst: syn::Expr,
+    pub chunks: Vec<lower_ast::Chunk>,
+}
+
+/// Writes formatted HTML to a `tokio::io::AsyncWrite`, returning a future of
+/// an `io::Result<()>`.
+///
+/// The output is written and flushed in chunks, one for every top-level
+/// element with embedded expressions, together with the static markup before
+/// it. The body of a top-level `@for` is written and flushed per iteration,
+/// also inside the elements around it. Values bound with a top-level `@let`
+/// stay in scope across chunks.
+///
+/// Requires the `tokio` feature.
+#[cfg(feature = "tokio")]
+#[proc_macro]
+pub fn write_async(input: TokenStream) -> TokenStream {
+    let WriteAsyncInput { dst, chunks } =
+        parse_expr_input!(input as WriteAsyncInput);
+
+    let buf = chunks
+        .iter()
+        .any(lower_ast::Chunk::has_args)
+        .then(|| quote!(let mut __fhtml_buf = ::std::string::String::new();));
+    let writes = write_chunks(chunks);
+
+    let output = quote! {
+        async {
+            use ::fhtml::__private::{AsyncWriteDst as _, AsyncWriteExt as _};
+            match (#dst).as_async_write() {
+                __fhtml_dst => {
+                    #buf
+                    #writes
+                    ::std::io::Result::<()>::Ok(())
+                }
+            }
+        }
+    };
+
+    output.into()
+}
+
+/// Creates the statements of `fhtml::write_async!` that write the chunks,
+/// flushing after each chunk with formatting arguments and at the end.
+#[cfg(feature = "tokio")]
+fn write_chunks(chunks: Vec<lower_ast::Chunk>) -> proc_macro2::TokenStream {
+    let flush = quote! {
+        __fhtml_dst.flush().await?;
+    };
+    // Static markup is written with the next chunk, so it is only flushed on
+    // its own at the end.
+    let end = matches!(chunks.last(), Some(lower_ast::Chunk::Static(_)))
+        .then(|| flush.clone());
+    let writes = chunks.into_iter().map(|chunk| match chunk {
+        lower_ast::Chunk::Static(s) => {
+            let bytes = proc_macro2::Literal::byte_string(s.as_bytes());
+            quote! {
+                __fhtml_dst.write_all(#bytes).await?;
+            }
+        }
+        lower_ast::Chunk::Args(fmt, args) => quote! {
+            ::fhtml::__private::format_chunk(
+                &mut __fhtml_buf,
+                ::core::format_args!(#fmt, #(#args),*),
+            )?;
+            __fhtml_dst.write_all(__fhtml_buf.as_bytes()).await?;
+            #flush
+        },
+        lower_ast::Chunk::Let(local) => quote!(#local),
+        lower_ast::Chunk::For(pat, expr, body) => {
+            let body = write_chunks(body);
+            quote! {
+                for #pat in #expr {
+                    #body
+                }
+            }
+        }
+    });
+
+    quote! {
+        #(#writes)*
+        #end
+    }
+}
+
+pub(crate) struct IncludeInput {
+    pub path: syn::LitStr,
+    pub bindings: Vec<(syn::Ident, syn::Expr)>,
+}
+
+/// Creates an [`fmt::Arguments`] with formatted HTML from a template file.
+///
+/// The path is relative to `CARGO_MANIFEST_DIR`, and the file is written like
+/// the input of [`fhtml::format_args!`]. Errors in the template are reported
+/// with their line and column in the file, and the crate is rebuilt when the
+/// file changes.
+///
+/// Values can be bound for the template as `name = value` after the path,
+/// like `@let name = value;`.
+///
+/// [`fmt::Arguments`]: https://doc.rust-lang.org/stable/std/fmt/struct.Arguments.html
+/// [`fhtml::format_args!`]: https://docs.rs/fhtml/latest/fhtml/macro.format_args.html
+#[proc_macro]
+pub fn include(input: TokenStream) -> TokenStream {
+    let input = parse_expr_input!(input as IncludeInput);
+
+    include::include(input).unwrap_or_else(expr_error).into()
+}
+
+/// Expands `fhtml::format!`, creating a `String` with the length of the static
+/// markup and the size hints of the values as its initial capacity.
+#[doc(hidden)]
+#[proc_macro]
+pub fn __format(input: TokenStream) -> TokenStream {
+    let FormatArgsInput { nodes } = parse_expr_input!(input as FormatArgsInput);
+    let (fmt, args) =
+        lower_ast::lower_format_args(nodes, lower_ast::Uses::Once);
+
+    // The arguments are bound once so their size hints can be computed
+    // before formatting, while string literals are already in the format
+    // string.
+    let capacity = lower_ast::static_len(&fmt);
+    let indices = (0..args.len()).map(syn::Index::from).collect::<Vec<_>>();
+
+    let output = quote! {
+        match (#(#args,)*) {
+            __fhtml_args => ::fhtml::__private::format(
+                #capacity #(
+                    + ::fhtml::__private::SizeHint::size_hint(
+                        &__fhtml_args.#indices
+                    )
+                )*,
+                ::core::format_args!(#fmt, #(__fhtml_args.#indices),*),
+            ),
+        }
     };
 
     output.into()
@@ -62,12 +296,30 @@ pub(crate) struct ConcatInput {
 /// [`std::concat!`]: https://doc.rust-lang.org/stable/std/macro.concat.html
 #[proc_macro]
 pub fn concat(input: TokenStream) -> TokenStream {
-    let ConcatInput { segments } =
-        syn::parse_macro_input!(input as ConcatInput);
+    let ConcatInput { segments } = parse_expr_input!(input as ConcatInput);
 
     let output = quote! {
-        ::std::concat!(#(#segments),*)
+        ::core::concat!(#(#segments),*)
     };
 
     output.into()
 }
+
+/// Derives [`Display`] and `fhtml::Render` for a type, rendering the template
+/// in the `#[template(...)]` attribute.
+///
+/// The template is written like the input of [`fhtml::format!`], with `self`
+/// and the named fields of a struct in scope. The output is trusted markup,
+/// so it is not escaped when the value is embedded as text in another
+/// template, while it is escaped like any other value in attributes.
+///
+/// [`Display`]: https://doc.rust-lang.org/stable/std/fmt/trait.Display.html
+/// [`fhtml::format!`]: https://docs.rs/fhtml/latest/fhtml/macro.format.html
+#[proc_macro_derive(Render, attributes(template))]
+pub fn derive_render(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as syn::DeriveInput);
+
+    derive::derive_render(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
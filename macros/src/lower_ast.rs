@@ -1,4 +1,8 @@
-use crate::ast;
+use proc_macro2::{Span, TokenStream};
+use quote::{quote, quote_spanned, ToTokens};
+use syn::spanned::Spanned;
+
+use crate::{ast, html};
 
 /// A small, fine grained representation of an HTML node.
 ///
@@ -8,6 +12,10 @@ use crate::ast;
 pub(crate) enum NodeToken<V> {
     Doctype,
 
+    // Comment
+    CommentStart,
+    CommentEnd,
+
     // Opening tag
     OpeningTagStart,
     OpeningTagName(ast::DashIdent),
@@ -25,30 +33,280 @@ pub(crate) enum NodeToken<V> {
     AttrValueStartQuote,
     AttrValue(V),
     AttrValueEndQuote,
+    // A boolean attribute with an expression value, which is either present
+    // or absent
+    BoolAttr(ast::DashIdent, V),
+    // An optional attribute with an expression value, which is omitted when
+    // the value is `None`
+    OptionalAttr(ast::DashIdent, V),
+    // A class list that is not known at compile time
+    ClassList(ast::DashIdent, ast::ClassList<V>),
+    // The spread attributes of a tag, skipping the names of the tag's other
+    // attributes
+    Spread(Vec<ast::Spread>, Vec<String>),
+
+    // Component
+    Component(Box<ast::Component<V>>),
+
+    // Function call, `@path(args) { ... }`
+    Call(Box<ast::Call<V>>),
 
     // Stray value
     Value(V),
+
+    // Control flow, `@if`, `@for`, `@match` and `@let`
+    ControlFlow(Box<ast::ControlFlow<V>>),
 }
 
-impl<V> ast::Attr<V> {
+/// The context an argument is rendered in, see `fhtml::Context`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Context {
+    Text,
+    /// The value of the attribute with the name.
+    Attr(String),
+}
+
+impl Context {
+    /// Returns the context for the value of an attribute.
+    pub(crate) fn for_attr(name: &ast::DashIdent) -> Self {
+        Self::Attr(name.to_string())
+    }
+}
+
+impl ToTokens for Context {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        tokens.extend(match self {
+            Self::Text => quote!(::fhtml::Context::Text),
+            // `fhtml::Context::for_attr` knows which attributes contain URLs
+            // or documents, and is evaluated at compile time.
+            Self::Attr(name) => quote!({
+                const CTX: ::fhtml::Context = ::fhtml::Context::for_attr(#name);
+                CTX
+            }),
+        })
+    }
+}
+
+impl ast::ArgValue {
+    /// Converts the value into a formatting argument.
+    ///
+    /// String literals are passed as-is, while expressions are rendered through
+    /// `fhtml::Render` in the given context, after applying any formatting
+    /// specifiers.
+    pub(crate) fn into_arg(self, ctx: Context) -> TokenStream {
+        match self {
+            Self::LitStr(lit) => lit.into_token_stream(),
+            Self::Expr { value, specs: None } => {
+                quote_spanned! {value.span()=>
+                    ::fhtml::__private::Rendered(&(#value), #ctx)
+                }
+            }
+            Self::Expr {
+                value,
+                specs: Some(specs),
+            } => {
+                let fmt =
+                    format!("{{:{}}}", specs.to_string().replace(' ', ""));
+                quote_spanned! {value.span()=>
+                    ::fhtml::__private::Rendered(
+                        &::core::format_args!(#fmt, #value),
+                        #ctx,
+                    )
+                }
+            }
+        }
+    }
+}
+
+impl ast::ArgValue {
+    /// Converts the value into a segment, where string literals are static.
+    fn into_segment(self, ctx: Context) -> Segment {
+        match self {
+            Self::LitStr(lit) => Segment::Static(lit.value()),
+            value => Segment::Arg(value.into_arg(ctx)),
+        }
+    }
+
+    /// Converts the value of a boolean attribute into a formatting argument,
+    /// which writes the attribute if the value is `true`.
+    fn into_bool_attr_arg(self, name: &ast::DashIdent) -> TokenStream {
+        let Self::Expr { value, .. } = self else {
+            unreachable!("string literals are written as regular attributes")
+        };
+        let attr = format!(" {}", name);
+        quote_spanned! {value.span()=>
+            if #value { #attr } else { "" }
+        }
+    }
+}
+
+impl ast::ArgValue {
+    /// Converts the value of an optional attribute into a formatting argument,
+    /// which writes the attribute if the value is `Some`.
+    fn into_optional_attr_arg(self, name: &ast::DashIdent) -> TokenStream {
+        let Self::Expr { value, .. } = self else {
+            unreachable!("string literals are written as regular attributes")
+        };
+        let ctx = Context::for_attr(name);
+        let attr = format!(" {}=", name);
+        quote_spanned! {value.span()=>
+            ::fhtml::__private::OptionalAttr(#attr, &(#value), #ctx)
+        }
+    }
+}
+
+impl ast::ClassList<ast::ArgValue> {
+    /// Converts the class list into a formatting argument, which writes the
+    /// attribute with the active class names, if there are any.
+    fn into_arg(self, name: &ast::DashIdent) -> TokenStream {
+        let ctx = Context::for_attr(name);
+        let attr = format!(" {}=", name);
+        let items = self.items.into_iter().map(|item| {
+            let value = match item.value {
+                ast::ArgValue::LitStr(lit) => {
+                    quote!(::core::option::Option::Some(#lit))
+                }
+                ast::ArgValue::Expr { value, .. } => {
+                    quote_spanned! {value.span()=>
+                        ::fhtml::__private::AsClass::as_class(&(#value))
+                    }
+                }
+            };
+            match item.cond {
+                Some(cond) => quote! {
+                    if #cond { #value } else { ::core::option::Option::None }
+                },
+                None => value,
+            }
+        });
+        quote! {
+            ::fhtml::__private::ClassList(#attr, &[#(#items),*], #ctx)
+        }
+    }
+}
+
+/// Converts the spread attributes of a tag into a formatting argument, which
+/// writes every attribute not named in `static_names` or by an earlier pair.
+fn spreads_into_arg(
+    spreads: Vec<ast::Spread>,
+    static_names: Vec<String>,
+    uses: Uses,
+) -> TokenStream {
+    let w = syn::Ident::new("w", Span::mixed_site());
+    let warning = spreads
+        .first()
+        .filter(|_| !static_names.is_empty())
+        .map(|spread| collision_warning(spread.expr.span(), &static_names));
+    let writes = spreads.into_iter().map(|spread| {
+        let expr = spread.expr;
+        quote_spanned!(expr.span()=> #w.write_attrs(#expr)?;)
+    });
+    let f = formatter();
+    template(
+        quote! {
+            ::fhtml::__private::write_spread(
+                #f,
+                &[#(#static_names),*],
+                |#w| {
+                    #warning
+                    #(#writes)*
+                    ::core::result::Result::Ok(())
+                },
+            )
+        },
+        uses,
+    )
+}
+
+impl<V: ast::Value> ast::Attr<V> {
     /// Converts an HTML attribute into a set of NodeTokens.
-    pub(crate) fn into_node_tokens(self) -> [NodeToken<V>; 6] {
-        [
-            NodeToken::AttrStartSpace,
-            NodeToken::AttrName(self.name),
-            NodeToken::AttrEqSep,
-            NodeToken::AttrValueStartQuote,
-            NodeToken::AttrValue(self.value),
-            NodeToken::AttrValueEndQuote,
-        ]
+    pub(crate) fn into_node_tokens(self) -> Vec<NodeToken<V>> {
+        let value = match self.value {
+            Some((_, ast::AttrValue::Single(value))) => value,
+            Some((_, ast::AttrValue::List(list))) => {
+                return match list.to_static() {
+                    // Omit the attribute if there are no class names.
+                    Some(names) if names.is_empty() => vec![],
+                    Some(names) => {
+                        let lit = syn::LitStr::new(
+                            &names.join(" "),
+                            list.bracket.span.join(),
+                        );
+                        value_attr_tokens(self.name, V::from_lit_str(lit))
+                    }
+                    None => vec![NodeToken::ClassList(self.name, list)],
+                };
+            }
+            None => {
+                return vec![
+                    NodeToken::AttrStartSpace,
+                    NodeToken::AttrName(self.name),
+                ];
+            }
+        };
+
+        if value.is_expr() && self.optional.is_some() {
+            vec![NodeToken::OptionalAttr(self.name, value)]
+        } else if value.is_expr()
+            && html::is_boolean_attr(&self.name.to_string())
+        {
+            vec![NodeToken::BoolAttr(self.name, value)]
+        } else {
+            value_attr_tokens(self.name, value)
+        }
     }
 }
 
-impl<V> ast::Tag<V> {
+/// Creates the NodeTokens of an attribute with a value, `name="value"`.
+fn value_attr_tokens<V>(name: ast::DashIdent, value: V) -> Vec<NodeToken<V>> {
+    vec![
+        NodeToken::AttrStartSpace,
+        NodeToken::AttrName(name),
+        NodeToken::AttrEqSep,
+        NodeToken::AttrValueStartQuote,
+        NodeToken::AttrValue(value),
+        NodeToken::AttrValueEndQuote,
+    ]
+}
+
+/// Creates statements that warn about spread pairs colliding with the
+/// attributes written on the tag, which are skipped when rendering.
+///
+/// Procedural macros cannot emit warnings on stable Rust, so this uses an
+/// item marked as `#[deprecated]`, which can be allowed with
+/// `#[allow(deprecated)]`.
+fn collision_warning(span: Span, static_names: &[String]) -> TokenStream {
+    let names = static_names
+        .iter()
+        .map(|name| format!("`{}`", name))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let note = format!(
+        "spread pairs named {} are skipped, since the attributes on the tag \
+         take precedence",
+        names
+    );
+    quote_spanned! {span=>
+        #[deprecated(note = #note)]
+        fn spread_may_collide_with_attrs() {}
+        spread_may_collide_with_attrs();
+    }
+}
+
+impl<V: ast::Value> ast::Tag<V> {
     /// Converts an HTML tag into a set of NodeTokens.
     pub(crate) fn into_node_tokens(self) -> Vec<NodeToken<V>> {
         match self {
-            Self::Opening { name, attrs, .. } => {
+            Self::Opening {
+                name,
+                attrs,
+                spreads,
+                ..
+            } => {
+                let static_names = attrs
+                    .iter()
+                    .map(|attr| attr.name.to_string().to_ascii_lowercase())
+                    .collect::<Vec<_>>();
                 let mut v = vec![
                     NodeToken::OpeningTagStart,
                     NodeToken::OpeningTagName(name),
@@ -56,6 +314,9 @@ impl<V> ast::Tag<V> {
                 for attr in attrs {
                     v.extend(attr.into_node_tokens())
                 }
+                if !spreads.is_empty() {
+                    v.push(NodeToken::Spread(spreads, static_names));
+                }
                 v.push(NodeToken::OpeningTagEnd);
                 v
             }
@@ -68,13 +329,564 @@ impl<V> ast::Tag<V> {
     }
 }
 
-impl<V> ast::Node<V> {
+impl<V: ast::Value> ast::Node<V> {
     /// Converts an HTML node into a set of NodeTokens.
     pub(crate) fn into_node_tokens(self) -> Vec<NodeToken<V>> {
         match self {
             ast::Node::Doctype(_) => vec![NodeToken::Doctype],
+            ast::Node::Comment(comment) => vec![
+                NodeToken::CommentStart,
+                NodeToken::Value(V::from_lit_str(comment.text)),
+                NodeToken::CommentEnd,
+            ],
             ast::Node::Tag(tag) => tag.into_node_tokens(),
+            ast::Node::Component(component) => {
+                vec![NodeToken::Component(component)]
+            }
+            ast::Node::Call(call) => vec![NodeToken::Call(call)],
+            // Blocks that are not overridden are written in place.
+            ast::Node::Block(block) => block
+                .nodes
+                .into_iter()
+                .flat_map(ast::Node::into_node_tokens)
+                .collect(),
             ast::Node::Value(value) => vec![NodeToken::Value(value)],
+            ast::Node::ControlFlow(control_flow) => {
+                vec![NodeToken::ControlFlow(control_flow)]
+            }
+        }
+    }
+}
+
+/// A piece of the output of a template.
+#[cfg_attr(test, derive(Debug))]
+pub(crate) enum Segment {
+    /// Markup that is known at compile time, including string literals.
+    Static(String),
+    /// A formatting argument.
+    Arg(TokenStream),
+}
+
+/// How many times the formatting arguments of a template are displayed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Uses {
+    /// The arguments are displayed once, e.g. by `fhtml::format!`, so
+    /// control flow can move the values it captures.
+    Once,
+    /// The arguments can be displayed any number of times, e.g. the
+    /// `fmt::Arguments` of `fhtml::format_args!`, which can be copied.
+    Many,
+}
+
+/// Lowers nodes into static segments and formatting arguments, in the order
+/// they are written, for writers displaying the arguments once.
+#[cfg(feature = "std")]
+pub(crate) fn lower_segments(
+    nodes: Vec<ast::Node<ast::ArgValue>>,
+) -> Vec<Segment> {
+    lower_node_tokens(
+        &mut nodes.into_iter().flat_map(ast::Node::into_node_tokens),
+        Uses::Once,
+    )
+}
+
+/// Lowers nodes into a format string and its arguments.
+pub(crate) fn lower_format_args(
+    nodes: Vec<ast::Node<ast::ArgValue>>,
+    uses: Uses,
+) -> (String, Vec<TokenStream>) {
+    format_args(lower_node_tokens(
+        &mut nodes.into_iter().flat_map(ast::Node::into_node_tokens),
+        uses,
+    ))
+}
+
+/// Joins segments into a format string and its arguments.
+fn format_args(segments: Vec<Segment>) -> (String, Vec<TokenStream>) {
+    let mut fmt = String::new();
+    let mut args = Vec::new();
+    for segment in segments {
+        match segment {
+            Segment::Static(s) => {
+                fmt.push_str(&s.replace('{', "{{").replace('}', "}}"))
+            }
+            Segment::Arg(arg) => {
+                fmt.push_str("{}");
+                args.push(arg);
+            }
+        }
+    }
+    (fmt, args)
+}
+
+/// A part of the output of a template that is written at once, by writers
+/// that flush between top-level elements.
+#[cfg(feature = "tokio")]
+pub(crate) enum Chunk {
+    /// Markup that is known at compile time, which is written with the next
+    /// chunk.
+    Static(String),
+    /// A top-level element with formatting arguments, as a format string and
+    /// its arguments, including the static markup before it.
+    Args(String, Vec<TokenStream>),
+    /// A top-level `@let`, which is in scope for the chunks after it.
+    Let(Box<syn::Local>),
+    /// A top-level `@for`, whose body is written once per iteration.
+    For(Box<syn::Pat>, Box<syn::Expr>, Vec<Chunk>),
+}
+
+#[cfg(feature = "tokio")]
+impl Chunk {
+    /// Returns `true` if the chunk, or a chunk in its body, has formatting
+    /// arguments.
+    pub(crate) fn has_args(&self) -> bool {
+        match self {
+            Self::Args(..) => true,
+            Self::For(_, _, body) => body.iter().any(Self::has_args),
+            Self::Static(_) | Self::Let(_) => false,
+        }
+    }
+}
+
+/// Lowers nodes into chunks, one for each balanced top-level element with
+/// formatting arguments, merging static markup into the chunk after it.
+///
+/// Elements containing a `@for` are split, so the body of the loop is
+/// written per iteration rather than buffered as a whole.
+#[cfg(feature = "tokio")]
+pub(crate) fn lower_chunks(nodes: Vec<ast::Node<ast::ArgValue>>) -> Vec<Chunk> {
+    let mut chunks = Vec::new();
+    let mut acc = String::new();
+    push_chunks(&mut chunks, &mut acc, flatten_blocks(nodes));
+    if !acc.is_empty() {
+        chunks.push(Chunk::Static(acc));
+    }
+
+    chunks
+}
+
+/// Replaces blocks that are not overridden with their nodes, which are
+/// written in place.
+#[cfg(feature = "tokio")]
+fn flatten_blocks(
+    nodes: Vec<ast::Node<ast::ArgValue>>,
+) -> Vec<ast::Node<ast::ArgValue>> {
+    nodes
+        .into_iter()
+        .flat_map(|node| match node {
+            ast::Node::Block(block) => flatten_blocks(block.nodes),
+            node => vec![node],
+        })
+        .collect()
+}
+
+/// Returns the number of nodes up to and including the closing tag of the
+/// element opened by the first node, or `None` if it is not an element with
+/// a closing tag.
+#[cfg(feature = "tokio")]
+fn element_len(nodes: &[ast::Node<ast::ArgValue>]) -> Option<usize> {
+    let mut depth = 0usize;
+    for (i, node) in nodes.iter().enumerate() {
+        match node {
+            ast::Node::Tag(ast::Tag::Opening {
+                name,
+                self_closing_slash: None,
+                ..
+            }) if !html::is_void_element(&name.to_string()) => depth += 1,
+            ast::Node::Tag(ast::Tag::Closing { .. }) => {
+                depth = depth.checked_sub(1)?;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            _ if i == 0 => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Adds the chunks of nodes, keeping static markup in `acc` until the next
+/// chunk with formatting arguments.
+#[cfg(feature = "tokio")]
+fn push_chunks(
+    chunks: &mut Vec<Chunk>,
+    acc: &mut String,
+    mut nodes: Vec<ast::Node<ast::ArgValue>>,
+) {
+    while !nodes.is_empty() {
+        let len = element_len(&nodes).unwrap_or(1);
+        let mut group = nodes.drain(..len).collect::<Vec<_>>();
+
+        if len > 1 && group.iter().any(is_for) {
+            // The element is split into its tags and children, so the loop
+            // is written on its own.
+            let closing = group.pop().into_iter().collect();
+            let children = group.split_off(1);
+            push_chunk(chunks, acc, group);
+            push_chunks(chunks, acc, children);
+            push_chunk(chunks, acc, closing);
+            continue;
+        }
+
+        if len > 1 {
+            push_chunk(chunks, acc, group);
+            continue;
+        }
+
+        match group.remove(0) {
+            ast::Node::ControlFlow(control_flow) => match *control_flow {
+                ast::ControlFlow::Let(ast::Let { local }) => {
+                    chunks.push(Chunk::Let(Box::new(local)));
+                }
+                ast::ControlFlow::For(ast::For {
+                    pat, expr, body, ..
+                }) => {
+                    if !acc.is_empty() {
+                        chunks.push(Chunk::Static(std::mem::take(acc)));
+                    }
+                    chunks.push(Chunk::For(
+                        Box::new(pat),
+                        Box::new(expr),
+                        lower_chunks(body),
+                    ));
+                }
+                control_flow => push_chunk(
+                    chunks,
+                    acc,
+                    vec![ast::Node::ControlFlow(Box::new(control_flow))],
+                ),
+            },
+            node => push_chunk(chunks, acc, vec![node]),
+        }
+    }
+}
+
+/// Returns `true` if the node is a `@for`.
+#[cfg(feature = "tokio")]
+fn is_for(node: &ast::Node<ast::ArgValue>) -> bool {
+    matches!(node, ast::Node::ControlFlow(control_flow)
+        if matches!(**control_flow, ast::ControlFlow::For(_)))
+}
+
+/// Adds the nodes to `acc` if they are static, or otherwise as a chunk of
+/// their own, together with the static markup in `acc`.
+#[cfg(feature = "tokio")]
+fn push_chunk(
+    chunks: &mut Vec<Chunk>,
+    acc: &mut String,
+    nodes: Vec<ast::Node<ast::ArgValue>>,
+) {
+    let segments = lower_segments(nodes);
+    if segments.iter().all(|s| matches!(s, Segment::Static(_))) {
+        for segment in segments {
+            if let Segment::Static(s) = segment {
+                acc.push_str(&s);
+            }
+        }
+        return;
+    }
+
+    let mut prefixed = vec![Segment::Static(std::mem::take(acc))];
+    prefixed.extend(segments);
+    let (fmt, args) = format_args(prefixed);
+    chunks.push(Chunk::Args(fmt, args));
+}
+
+/// Returns the length of the output of a format string, excluding its
+/// arguments.
+pub(crate) fn static_len(fmt: &str) -> usize {
+    let mut len = 0;
+    let mut bytes = fmt.bytes();
+    while let Some(b) = bytes.next() {
+        match b {
+            // `{{` and `}}` are written as a single brace, while `{}` is an
+            // argument.
+            b'{' | b'}' => {
+                if bytes.next() == Some(b) {
+                    len += 1;
+                }
+            }
+            _ => len += 1,
+        }
+    }
+    len
+}
+
+/// Lowers node tokens into static segments and formatting arguments.
+fn lower_node_tokens(
+    tokens: &mut dyn Iterator<Item = NodeToken<ast::ArgValue>>,
+    uses: Uses,
+) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut attr_ctx = Context::Attr(String::new());
+
+    while let Some(token) = tokens.next() {
+        let arg = match token {
+            NodeToken::AttrValue(value) => value.into_segment(attr_ctx.clone()),
+            NodeToken::Value(value) => value.into_segment(Context::Text),
+            NodeToken::BoolAttr(name, value) => {
+                Segment::Arg(value.into_bool_attr_arg(&name))
+            }
+            NodeToken::OptionalAttr(name, value) => {
+                Segment::Arg(value.into_optional_attr_arg(&name))
+            }
+            NodeToken::ClassList(name, list) => {
+                Segment::Arg(list.into_arg(&name))
+            }
+            NodeToken::Spread(spreads, static_names) => {
+                Segment::Arg(spreads_into_arg(spreads, static_names, uses))
+            }
+            NodeToken::Component(component) => {
+                Segment::Arg(component.into_arg())
+            }
+            NodeToken::Call(call) => Segment::Arg(call.into_arg()),
+            NodeToken::ControlFlow(control_flow) => match *control_flow {
+                ast::ControlFlow::Let(ast::Let { local }) => {
+                    // The binding is in scope for the rest of the tokens, so
+                    // they are written from within the same closure.
+                    let rest = write_node_tokens(tokens);
+                    Segment::Arg(template(
+                        quote! {
+                            #local
+                            #rest
+                        },
+                        uses,
+                    ))
+                }
+                control_flow => Segment::Arg(control_flow.into_arg(uses)),
+            },
+            token => {
+                if let NodeToken::AttrName(name) = &token {
+                    attr_ctx = Context::for_attr(name);
+                }
+                Segment::Static(token.to_string())
+            }
+        };
+
+        match (arg, segments.last_mut()) {
+            (Segment::Static(s), Some(Segment::Static(acc))) => {
+                acc.push_str(&s)
+            }
+            (segment, _) => segments.push(segment),
+        }
+    }
+
+    segments
+}
+
+/// The identifier of the formatter in the generated closures.
+pub(crate) fn formatter() -> syn::Ident {
+    // Using a mixed site span prevents conflicts with user-defined bindings.
+    syn::Ident::new("f", Span::mixed_site())
+}
+
+/// Wraps a closure body into a template, which implements `Display`.
+fn template(body: TokenStream, uses: Uses) -> TokenStream {
+    let f = formatter();
+    match uses {
+        Uses::Once => quote! {
+            ::fhtml::__private::TemplateOnce::new(|#f| { #body })
+        },
+        Uses::Many => quote! {
+            ::fhtml::__private::Template::new(|#f| { #body })
+        },
+    }
+}
+
+/// Creates an expression that writes the node tokens into the formatter.
+///
+/// The arguments are created each time the expression is evaluated, so they
+/// are displayed once.
+fn write_node_tokens(
+    tokens: &mut dyn Iterator<Item = NodeToken<ast::ArgValue>>,
+) -> TokenStream {
+    let f = formatter();
+    let (fmt, args) = format_args(lower_node_tokens(tokens, Uses::Once));
+    quote! {
+        #f.write_fmt(::core::format_args!(#fmt, #(#args),*))
+    }
+}
+
+/// Creates an expression that writes the nodes into the formatter.
+fn write_nodes(nodes: Vec<ast::Node<ast::ArgValue>>) -> TokenStream {
+    write_node_tokens(
+        &mut nodes.into_iter().flat_map(ast::Node::into_node_tokens),
+    )
+}
+
+/// Creates a `fhtml::Slot`, which writes the nodes into the formatter.
+fn slot(nodes: Vec<ast::Node<ast::ArgValue>>) -> TokenStream {
+    let f = formatter();
+    let body = write_nodes(nodes);
+    quote!(::fhtml::Slot::new(&|#f| #body))
+}
+
+impl ast::Component<ast::ArgValue> {
+    /// Converts the component into a formatting argument, which renders the
+    /// component struct created from the props and children.
+    fn into_arg(self) -> TokenStream {
+        let Self {
+            name,
+            attrs,
+            spreads,
+            children,
+        } = self;
+
+        let fields = attrs.into_iter().map(|attr| {
+            let ident = attr.name.0.into_iter().next().unwrap();
+            match attr.value {
+                Some((_, ast::AttrValue::Single(value))) => {
+                    quote!(#ident: #value,)
+                }
+                Some((_, ast::AttrValue::List(_))) => {
+                    unreachable!("class lists are rejected by the analyzer")
+                }
+                // A bare prop is `true`, like a boolean attribute.
+                None => quote_spanned!(ident.span()=> #ident: true,),
+            }
+        });
+
+        let nodes = (!children.nodes.is_empty()).then(|| {
+            let ident = syn::Ident::new("children", name.span());
+            let slot = slot(children.nodes);
+            quote!(#ident: #slot,)
+        });
+
+        let slots = children.slots.into_iter().map(|named| {
+            let ident = named.name;
+            let slot = slot(named.nodes);
+            quote!(#ident: #slot,)
+        });
+
+        let base = spreads.into_iter().next().map(|spread| {
+            let expr = spread.expr;
+            quote!(..#expr)
+        });
+
+        quote_spanned! {name.span()=>
+            ::fhtml::__private::RenderComponent(
+                &#name { #(#fields)* #nodes #(#slots)* #base }
+            )
+        }
+    }
+}
+
+impl ast::Call<ast::ArgValue> {
+    /// Converts the function call into a formatting argument, which renders
+    /// the returned value, passing the children as a trailing `fhtml::Slots`
+    /// argument.
+    fn into_arg(self) -> TokenStream {
+        let Self {
+            func,
+            args,
+            children,
+        } = self;
+
+        let args = args.into_iter();
+        let slots = children.map(|children| {
+            let nodes = slot(children.nodes);
+            let slots = children.slots.into_iter().map(|named| {
+                let name = named.name.to_string();
+                let slot = slot(named.nodes);
+                quote!((#name, #slot))
+            });
+            quote!(::fhtml::Slots::new(#nodes, &[#(#slots),*]))
+        });
+
+        let ctx = Context::Text;
+        quote_spanned! {func.span()=>
+            ::fhtml::__private::Rendered(&(#func(#(#args,)* #slots)), #ctx)
+        }
+    }
+}
+
+impl ast::ControlFlow<ast::ArgValue> {
+    /// Converts the control flow into a formatting argument.
+    fn into_arg(self, uses: Uses) -> TokenStream {
+        match self {
+            Self::If(if_) => template(if_.into_expr(), uses),
+            Self::For(ast::For {
+                pat, expr, body, ..
+            }) => {
+                let body = write_nodes(body);
+                template(
+                    quote! {
+                        for #pat in #expr {
+                            #body?;
+                        }
+                        ::core::result::Result::Ok(())
+                    },
+                    uses,
+                )
+            }
+            Self::Match(ast::Match { expr, arms, .. }) => {
+                let arms = arms.into_iter().map(|arm| {
+                    let ast::Arm { pat, guard, body } = arm;
+                    let guard = guard.map(|guard| quote!(if #guard));
+                    let body = write_nodes(body);
+                    quote!(#pat #guard => #body,)
+                });
+                template(
+                    quote! {
+                        match #expr {
+                            #(#arms)*
+                        }
+                    },
+                    uses,
+                )
+            }
+            Self::Let(_) => {
+                unreachable!(
+                    "`@let` is lowered together with the nodes after it"
+                )
+            }
+        }
+    }
+}
+
+/// Returns the `@if` of an `@else if`, or the nodes of an `@else`.
+fn into_else_if(
+    nodes: Vec<ast::Node<ast::ArgValue>>,
+) -> Result<ast::If<ast::ArgValue>, Vec<ast::Node<ast::ArgValue>>> {
+    match <[_; 1]>::try_from(nodes) {
+        Ok([ast::Node::ControlFlow(control_flow)]) => match *control_flow {
+            ast::ControlFlow::If(if_) => Ok(if_),
+            control_flow => {
+                Err(vec![ast::Node::ControlFlow(Box::new(control_flow))])
+            }
+        },
+        Ok(nodes) => Err(nodes.into()),
+        Err(nodes) => Err(nodes),
+    }
+}
+
+impl ast::If<ast::ArgValue> {
+    /// Converts the `@if` into an `if` expression writing into the formatter.
+    fn into_expr(self) -> TokenStream {
+        let Self {
+            cond,
+            then_branch,
+            else_branch,
+            ..
+        } = self;
+
+        let then_branch = write_nodes(then_branch);
+        let else_branch = match else_branch {
+            // An `@else if` is written from within the same closure.
+            Some(else_branch) => match into_else_if(else_branch) {
+                Ok(if_) => if_.into_expr(),
+                Err(else_branch) => write_nodes(else_branch),
+            },
+            None => quote!(::core::result::Result::Ok(())),
+        };
+
+        quote! {
+            if #cond {
+                #then_branch
+            } else {
+                #else_branch
+            }
         }
     }
 }
@@ -93,15 +905,82 @@ mod tests {
         }
     }
 
+    #[test]
+    fn static_len() {
+        assert_eq!(super::static_len("<p>{}</p>"), 7);
+        assert_eq!(super::static_len("{{}}{}"), 2);
+        assert_eq!(super::static_len("é"), 2);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn segments() {
+        let nodes = syn::parse::Parser::parse2(
+            crate::parse::parse_template,
+            quote! { <p class="a">"Hello, "{name}"! {}"</p> },
+        )
+        .unwrap();
+
+        let segments = lower_segments(nodes);
+        assert!(matches!(
+            segments.as_slice(),
+            [
+                Segment::Static(start),
+                Segment::Arg(_),
+                Segment::Static(end),
+            ] if start == "<p class=\"a\">Hello, " && end == "! {}</p>"
+        ));
+        assert_eq!(
+            format_args(segments).0,
+            "<p class=\"a\">Hello, {}! {{}}</p>"
+        );
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn chunks() {
+        let nodes = syn::parse::Parser::parse2(
+            crate::parse::parse_template,
+            quote! {
+                <main><br>
+                @let n = 1;
+                <p>{n}</p>
+                <ul>@for i in items { <li>{i}</li> }</ul>
+                </main>
+            },
+        )
+        .unwrap();
+
+        let chunks = lower_chunks(nodes);
+        assert!(matches!(
+            chunks.as_slice(),
+            [
+                Chunk::Let(_),
+                Chunk::Args(p, _),
+                Chunk::Static(ul),
+                Chunk::For(_, _, body),
+                Chunk::Static(end),
+            ] if p == "<main><br><p>{}</p>"
+                && ul == "<ul>"
+                && matches!(
+                    body.as_slice(),
+                    [Chunk::Args(li, _)] if li == "<li>{}</li>"
+                )
+                && end == "</ul></main>"
+        ));
+    }
+
     #[test]
     fn attrs() {
         assert_eq!(
             ast::Attr {
                 name: dash_ident!(foo),
-                eq_sep: syn::Token![=]([Span::mixed_site()]),
-                value: ast::LitValue::LitStr(syn::LitStr::new(
-                    "foo",
-                    Span::mixed_site()
+                optional: None,
+                value: Some((
+                    syn::Token![=]([Span::mixed_site()]),
+                    ast::AttrValue::Single(ast::LitValue::LitStr(
+                        syn::LitStr::new("foo", Span::mixed_site())
+                    )),
                 )),
             }
             .into_node_tokens(),
@@ -119,12 +998,138 @@ mod tests {
         )
     }
 
+    #[test]
+    fn bare_attrs() {
+        assert_eq!(
+            ast::Attr::<ast::LitValue> {
+                name: dash_ident!(disabled),
+                optional: None,
+                value: None,
+            }
+            .into_node_tokens(),
+            [
+                NodeToken::AttrStartSpace,
+                NodeToken::AttrName(dash_ident!(disabled)),
+            ]
+        )
+    }
+
+    #[test]
+    fn bool_attrs() {
+        let value = ast::LitValue::Expr(syn::parse_quote!(is_checked));
+        assert_eq!(
+            ast::Attr {
+                name: dash_ident!(checked),
+                optional: None,
+                value: Some((
+                    syn::Token![=]([Span::mixed_site()]),
+                    ast::AttrValue::Single(value.clone())
+                )),
+            }
+            .into_node_tokens(),
+            [NodeToken::BoolAttr(dash_ident!(checked), value)]
+        )
+    }
+
+    #[test]
+    fn optional_attrs() {
+        let value = ast::LitValue::Expr(syn::parse_quote!(title));
+        assert_eq!(
+            ast::Attr {
+                name: dash_ident!(title),
+                optional: Some(syn::Token![?]([Span::mixed_site()])),
+                value: Some((
+                    syn::Token![=]([Span::mixed_site()]),
+                    ast::AttrValue::Single(value.clone())
+                )),
+            }
+            .into_node_tokens(),
+            [NodeToken::OptionalAttr(dash_ident!(title), value)]
+        )
+    }
+
+    #[test]
+    fn static_class_list() {
+        let attr = syn::parse2::<ast::Attr<ast::LitValue>>(quote::quote! {
+            class=["foo bar", "", "baz", "foo"]
+        })
+        .unwrap();
+        assert_eq!(
+            attr.into_node_tokens()[4],
+            NodeToken::AttrValue(ast::LitValue::LitStr(syn::LitStr::new(
+                "foo bar baz",
+                Span::call_site()
+            )))
+        );
+
+        let attr = syn::parse2::<ast::Attr<ast::LitValue>>(quote::quote! {
+            class=[""]
+        })
+        .unwrap();
+        assert_eq!(attr.into_node_tokens(), []);
+    }
+
+    #[test]
+    fn dynamic_class_list() {
+        let attr = syn::parse2::<ast::Attr<ast::LitValue>>(quote::quote! {
+            class=["foo", "bar" => is_bar]
+        })
+        .unwrap();
+        assert!(matches!(
+            attr.into_node_tokens().as_slice(),
+            [NodeToken::ClassList(..)]
+        ));
+    }
+
+    #[test]
+    fn spread_attrs() {
+        let tag = syn::parse2::<ast::Tag<ast::LitValue>>(quote::quote! {
+            <div ID="foo" ..{attrs} disabled>
+        })
+        .unwrap();
+        assert_eq!(
+            tag.into_node_tokens().iter().rev().nth(1),
+            Some(&NodeToken::Spread(
+                vec![ast::Spread {
+                    dot2: syn::Token![..](Span::call_site()),
+                    expr: syn::parse_quote!(attrs),
+                }],
+                vec!["id".to_owned(), "disabled".to_owned()]
+            ))
+        );
+
+        let warning = |static_names: Vec<String>| {
+            spreads_into_arg(
+                vec![syn::parse_quote!(..{ attrs })],
+                static_names,
+                Uses::Once,
+            )
+            .to_string()
+            .contains("deprecated")
+        };
+        assert!(warning(vec!["id".to_owned()]));
+        assert!(!warning(vec![]));
+    }
+
+    #[test]
+    fn attr_context() {
+        assert_eq!(
+            Context::for_attr(&dash_ident!(data - href)),
+            Context::Attr("data-href".to_owned())
+        );
+        assert!(Context::for_attr(&dash_ident!(SRC))
+            .to_token_stream()
+            .to_string()
+            .contains("for_attr (\"SRC\")"));
+    }
+
     #[test]
     fn opening_tag() {
         assert_eq!(
             ast::Tag::<ast::LitValue>::Opening {
                 name: dash_ident!(foo),
                 attrs: vec![],
+                spreads: vec![],
                 self_closing_slash: None
             }
             .into_node_tokens(),
@@ -136,6 +1141,22 @@ mod tests {
         );
     }
 
+    #[test]
+    fn comment() {
+        let text = syn::LitStr::new(" foo ", Span::call_site());
+        assert_eq!(
+            ast::Node::<ast::LitValue>::Comment(ast::Comment {
+                text: text.clone()
+            })
+            .into_node_tokens(),
+            [
+                NodeToken::CommentStart,
+                NodeToken::Value(ast::LitValue::LitStr(text)),
+                NodeToken::CommentEnd,
+            ]
+        );
+    }
+
     #[test]
     fn closing_tag() {
         assert_eq!(
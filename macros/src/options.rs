@@ -0,0 +1,38 @@
+use crate::ast;
+
+/// Options of a macro invocation, set by inner attributes at the start of the
+/// input, e.g. `#![fhtml(minify)]`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Options {
+    /// Strips comments from the output.
+    pub minify: bool,
+    /// Checks that standard HTML elements are nested according to their
+    /// content model.
+    pub strict: bool,
+    /// Checks that element and attribute names are defined by the HTML
+    /// standard.
+    pub lint: bool,
+    /// Checks for common accessibility problems, e.g. images without
+    /// alternative text.
+    pub a11y: bool,
+}
+
+impl Options {
+    /// Applies the options to the parsed nodes.
+    pub(crate) fn apply<V>(&self, nodes: &mut Vec<ast::Node<V>>) {
+        if self.minify {
+            strip_comments(nodes);
+        }
+    }
+}
+
+/// Removes every comment, including comments in nested nodes.
+fn strip_comments<V>(nodes: &mut Vec<ast::Node<V>>) {
+    nodes.retain(|node| !matches!(node, ast::Node::Comment(_)));
+
+    for node in nodes {
+        for branch in node.branches_mut() {
+            strip_comments(branch);
+        }
+    }
+}
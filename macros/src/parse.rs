@@ -2,16 +2,23 @@ use std::fmt::Write;
 
 use quote::ToTokens;
 use syn::ext::IdentExt as _;
+use syn::parse::discouraged::Speculative as _;
 use syn::parse::{Parse, ParseStream};
 use syn::punctuated::Punctuated;
 
-use crate::analyze::analyze_nodes;
-use crate::{ast, lower_ast, ConcatInput, FormatArgsInput};
+use crate::analyze::{analyze_nodes, analyze_template, Errors};
+use crate::options::Options;
+use crate::{
+    ast, include, layout, lower_ast, ConcatInput, FormatArgsInput, IncludeInput,
+};
 
 mod kw {
     syn::custom_keyword!(DOCTYPE);
     syn::custom_keyword!(doctype);
     syn::custom_keyword!(html);
+    syn::custom_keyword!(slot);
+    syn::custom_keyword!(block);
+    syn::custom_keyword!(extends);
 }
 
 impl Parse for ast::DashIdent {
@@ -42,6 +49,38 @@ impl Parse for ast::Doctype {
     }
 }
 
+impl Parse for ast::Comment {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        input.parse::<syn::Token![<]>()?;
+        input.parse::<syn::Token![!]>()?;
+        input.parse::<syn::Token![-]>()?;
+        input.parse::<syn::Token![-]>()?;
+        let text = input.parse::<syn::LitStr>()?;
+        input.parse::<syn::Token![-]>()?;
+        input.parse::<syn::Token![-]>()?;
+        input.parse::<syn::Token![>]>()?;
+
+        let value = text.value();
+        if value.contains("--") {
+            return Err(syn::Error::new(
+                text.span(),
+                "comments must not contain `--`",
+            ));
+        }
+        if value.starts_with('>')
+            || value.starts_with("->")
+            || value.ends_with("<!-")
+        {
+            return Err(syn::Error::new(
+                text.span(),
+                "comments must not start with `>` or `->`, or end with `<!-`",
+            ));
+        }
+
+        Ok(Self { text })
+    }
+}
+
 impl Parse for ast::LitValue {
     fn parse(input: ParseStream) -> syn::Result<Self> {
         let lookahead = input.lookahead1();
@@ -81,20 +120,69 @@ impl Parse for ast::ArgValue {
     }
 }
 
+impl<V: Parse> Parse for ast::ClassItem<V> {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let value = input.parse()?;
+
+        let mut cond = None;
+        if input.parse::<Option<syn::Token![=>]>>()?.is_some() {
+            cond = Some(input.parse()?);
+        }
+
+        Ok(Self { value, cond })
+    }
+}
+
+impl<V: Parse> Parse for ast::ClassList<V> {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let content;
+        let bracket = syn::bracketed!(content in input);
+        let items = Punctuated::parse_terminated(&content)?;
+
+        Ok(Self { bracket, items })
+    }
+}
+
+impl<V: Parse> Parse for ast::AttrValue<V> {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(syn::token::Bracket) {
+            Ok(Self::List(input.parse()?))
+        } else {
+            Ok(Self::Single(input.parse()?))
+        }
+    }
+}
+
 impl<V: Parse> Parse for ast::Attr<V> {
     fn parse(input: ParseStream) -> syn::Result<Self> {
         let name = input.parse()?;
-        let eq_sep = input.parse()?;
-        let value = input.parse()?;
+        let optional = input.parse::<Option<syn::Token![?]>>()?;
+
+        let mut value = None;
+        if optional.is_some() || input.peek(syn::Token![=]) {
+            value = Some((input.parse()?, input.parse()?));
+        }
 
         Ok(Self {
             name,
-            eq_sep,
+            optional,
             value,
         })
     }
 }
 
+impl Parse for ast::Spread {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let dot2 = input.parse()?;
+
+        let content;
+        syn::braced!(content in input);
+        let expr = content.parse()?;
+
+        Ok(Self { dot2, expr })
+    }
+}
+
 impl<V: Parse> Parse for ast::Tag<V> {
     fn parse(input: ParseStream) -> syn::Result<Self> {
         input.parse::<syn::Token![<]>()?;
@@ -109,10 +197,15 @@ impl<V: Parse> Parse for ast::Tag<V> {
         let name = input.parse()?;
 
         let mut attrs = Vec::new();
+        let mut spreads = Vec::new();
         while !(input.peek(syn::Token![>])
             || (input.peek(syn::Token![/]) && input.peek2(syn::Token![>])))
         {
-            attrs.push(input.parse()?);
+            if input.peek(syn::Token![..]) {
+                spreads.push(input.parse()?);
+            } else {
+                attrs.push(input.parse()?);
+            }
         }
 
         let self_closing_slash = input.parse()?;
@@ -121,21 +214,249 @@ impl<V: Parse> Parse for ast::Tag<V> {
         Ok(Self::Opening {
             name,
             attrs,
+            spreads,
             self_closing_slash,
         })
     }
 }
 
+/// Parses nodes until the end of the input.
+fn parse_nodes<V: Parse>(input: ParseStream) -> syn::Result<Vec<ast::Node<V>>> {
+    let mut nodes = Vec::new();
+    let mut errors = Errors::default();
+    while !input.is_empty() {
+        nodes.extend(parse_or_recover(input, &mut errors));
+    }
+    errors.finish()?;
+    Ok(nodes)
+}
+
+/// Parses a node, or records its error and skips to the next node, so that
+/// the errors of the following nodes are reported as well.
+fn parse_or_recover<T: Parse>(
+    input: ParseStream,
+    errors: &mut Errors,
+) -> Option<T> {
+    let fork = input.fork();
+    let result = fork.parse();
+    // The tokens parsed before an error are skipped as well, e.g. the children
+    // of a component, which report their own errors.
+    let progressed = fork.cursor() != input.cursor();
+    input.advance_to(&fork);
+
+    match result {
+        Ok(node) => Some(node),
+        Err(err) => {
+            errors.push(err);
+            skip_node(input, !progressed);
+            None
+        }
+    }
+}
+
+/// Skips the token trees up to the start of the next tag, `<`, or the next
+/// control flow or call, `@`, skipping at least one token tree if `force`.
+fn skip_node(input: ParseStream, force: bool) {
+    let _ = input.step(|cursor| {
+        let is_node_start = |cursor: syn::buffer::Cursor| {
+            cursor
+                .punct()
+                .is_some_and(|(p, _)| matches!(p.as_char(), '<' | '@'))
+        };
+
+        let mut rest = *cursor;
+        if force {
+            if let Some((_, next)) = rest.token_tree() {
+                rest = next;
+            }
+        }
+        while !is_node_start(rest) {
+            match rest.token_tree() {
+                Some((_, next)) => rest = next,
+                None => break,
+            }
+        }
+        Ok(((), rest))
+    });
+}
+
+/// Parses nodes enclosed in braces, `{ ... }`.
+fn parse_braced_nodes<V: Parse>(
+    input: ParseStream,
+) -> syn::Result<Vec<ast::Node<V>>> {
+    let content;
+    syn::braced!(content in input);
+    parse_nodes(&content)
+}
+
+impl<V: Parse> Parse for ast::If<V> {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let if_token = input.parse()?;
+        let cond = syn::Expr::parse_without_eager_brace(input)?;
+        // The else branch is parsed even if the then branch has errors, since
+        // it would be mistaken for a node otherwise.
+        let mut errors = Errors::default();
+        let then_branch =
+            errors.check(parse_braced_nodes(input)).unwrap_or_default();
+
+        let mut else_branch = None;
+        if input.peek(syn::Token![@]) && input.peek2(syn::Token![else]) {
+            input.parse::<syn::Token![@]>()?;
+            input.parse::<syn::Token![else]>()?;
+
+            else_branch = if input.peek(syn::Token![if]) {
+                errors.check(input.parse()).map(|if_| {
+                    vec![ast::Node::ControlFlow(Box::new(
+                        ast::ControlFlow::If(if_),
+                    ))]
+                })
+            } else {
+                errors.check(parse_braced_nodes(input))
+            };
+        }
+        errors.finish()?;
+
+        Ok(Self {
+            if_token,
+            cond,
+            then_branch,
+            else_branch,
+        })
+    }
+}
+
+impl<V: Parse> Parse for ast::For<V> {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let for_token = input.parse()?;
+        let pat = syn::Pat::parse_multi_with_leading_vert(input)?;
+        input.parse::<syn::Token![in]>()?;
+        let expr = syn::Expr::parse_without_eager_brace(input)?;
+        let body = parse_braced_nodes(input)?;
+
+        Ok(Self {
+            for_token,
+            pat,
+            expr,
+            body,
+        })
+    }
+}
+
+impl<V: Parse> Parse for ast::Match<V> {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let match_token = input.parse()?;
+        let expr = syn::Expr::parse_without_eager_brace(input)?;
+
+        let content;
+        syn::braced!(content in input);
+
+        let mut arms = Vec::new();
+        let mut errors = Errors::default();
+        while !content.is_empty() {
+            let pat = syn::Pat::parse_multi_with_leading_vert(&content)?;
+            let mut guard = None;
+            if content.parse::<Option<syn::Token![if]>>()?.is_some() {
+                guard = Some(content.parse()?);
+            }
+            content.parse::<syn::Token![=>]>()?;
+            let body = errors
+                .check(parse_braced_nodes(&content))
+                .unwrap_or_default();
+            content.parse::<Option<syn::Token![,]>>()?;
+
+            arms.push(ast::Arm { pat, guard, body });
+        }
+        errors.finish()?;
+
+        Ok(Self {
+            match_token,
+            expr,
+            arms,
+        })
+    }
+}
+
+impl Parse for ast::Let {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        match input.parse()? {
+            syn::Stmt::Local(local) => Ok(Self { local }),
+            stmt => Err(syn::Error::new_spanned(stmt, "expected `let`")),
+        }
+    }
+}
+
+impl<V: Parse> Parse for ast::ControlFlow<V> {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        input.parse::<syn::Token![@]>()?;
+
+        let lookahead = input.lookahead1();
+        if lookahead.peek(syn::Token![if]) {
+            Ok(Self::If(input.parse()?))
+        } else if lookahead.peek(syn::Token![for]) {
+            Ok(Self::For(input.parse()?))
+        } else if lookahead.peek(syn::Token![match]) {
+            Ok(Self::Match(input.parse()?))
+        } else if lookahead.peek(syn::Token![let]) {
+            Ok(Self::Let(input.parse()?))
+        } else {
+            Err(lookahead.error())
+        }
+    }
+}
+
 impl<V: Parse> Parse for ast::Node<V> {
     fn parse(input: ParseStream) -> syn::Result<Self> {
         let lookahead = input.lookahead1();
-        if lookahead.peek(syn::Token![<])
+        if lookahead.peek(syn::Token![@]) {
+            if input.peek2(syn::Token![if])
+                || input.peek2(syn::Token![for])
+                || input.peek2(syn::Token![match])
+                || input.peek2(syn::Token![let])
+            {
+                Ok(Self::ControlFlow(Box::new(input.parse()?)))
+            } else if input.peek2(kw::block) && input.peek3(syn::Ident) {
+                Ok(Self::Block(input.parse()?))
+            } else if input.peek2(kw::extends) && input.peek3(syn::LitStr) {
+                Err(input
+                    .error("`@extends` must be at the start of the template"))
+            } else {
+                Ok(Self::Call(Box::new(input.parse()?)))
+            }
+        } else if lookahead.peek(syn::Token![<])
             && input.peek2(syn::Token![!])
             && (input.peek3(kw::DOCTYPE) || input.peek3(kw::doctype))
         {
             Ok(Self::Doctype(input.parse()?))
+        } else if lookahead.peek(syn::Token![<])
+            && input.peek2(syn::Token![!])
+            && input.peek3(syn::Token![-])
+        {
+            Ok(Self::Comment(input.parse()?))
         } else if lookahead.peek(syn::Token![<]) {
-            Ok(Self::Tag(input.parse()?))
+            match input.parse()? {
+                ast::Tag::Opening {
+                    name,
+                    attrs,
+                    spreads,
+                    self_closing_slash,
+                } if name.is_component() => {
+                    let name = name.as_ident().unwrap().clone();
+                    let children = match self_closing_slash {
+                        Some(_) => ast::Children {
+                            nodes: vec![],
+                            slots: vec![],
+                        },
+                        None => parse_children(input, Some(&name))?,
+                    };
+                    Ok(Self::Component(Box::new(ast::Component {
+                        name,
+                        attrs,
+                        spreads,
+                        children,
+                    })))
+                }
+                tag => Ok(Self::Tag(tag)),
+            }
         } else if lookahead.peek(syn::LitStr)
             || lookahead.peek(syn::token::Brace)
         {
@@ -146,27 +467,296 @@ impl<V: Parse> Parse for ast::Node<V> {
     }
 }
 
-impl Parse for FormatArgsInput {
+impl Parse for Options {
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        let mut fmt = String::new();
-        let mut args = Vec::new();
-        let mut nodes = Vec::new();
+        let mut options = Self::default();
+
+        for attr in input.call(syn::Attribute::parse_inner)? {
+            if !attr.path().is_ident("fhtml") {
+                return Err(syn::Error::new_spanned(
+                    attr.path(),
+                    "expected `fhtml`",
+                ));
+            }
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("minify") {
+                    options.minify = true;
+                    Ok(())
+                } else if meta.path.is_ident("strict") {
+                    options.strict = true;
+                    Ok(())
+                } else if meta.path.is_ident("lint") {
+                    options.lint = true;
+                    Ok(())
+                } else if meta.path.is_ident("a11y") {
+                    options.a11y = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("unknown option"))
+                }
+            })?;
+        }
+
+        Ok(options)
+    }
+}
+
+impl<V: Parse> Parse for ast::Block<V> {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        input.parse::<syn::Token![@]>()?;
+        input.parse::<kw::block>()?;
+        let name = input.call(syn::Ident::parse_any)?;
+        let nodes = parse_braced_nodes(input)?;
+
+        Ok(Self { name, nodes })
+    }
+}
+
+impl<V: Parse> Parse for ast::Extends<V> {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        input.parse::<syn::Token![@]>()?;
+        input.parse::<kw::extends>()?;
+        let path = input.parse()?;
 
+        // Only blocks are allowed after `@extends`, since the layout
+        // determines where they are written.
+        let is_block = |input: ParseStream| {
+            input.peek(syn::Token![@]) && input.peek2(kw::block)
+        };
+        let mut blocks = Vec::new();
+        let mut errors = Errors::default();
         while !input.is_empty() {
-            let node = input.parse::<ast::Node<ast::ArgValue>>()?;
-            args.extend(node.get_all_values());
-            nodes.push(node);
+            if !is_block(input) {
+                errors.push(
+                    input.error("only blocks are allowed after `@extends`"),
+                );
+                while !(input.is_empty() || is_block(input)) {
+                    input.parse::<proc_macro2::TokenTree>()?;
+                }
+                continue;
+            }
+            blocks.extend(
+                parse_or_recover(input, &mut errors).map(ast::Node::Block),
+            );
         }
+        errors.finish()?;
 
-        analyze_nodes(&nodes)?;
+        Ok(Self { path, blocks })
+    }
+}
 
-        for node in nodes {
-            for token in node.into_node_tokens() {
-                let _ = write!(fmt, "{}", token);
+/// Parses the closing tag `</name>`, or `</slot:name>` for a named slot.
+///
+/// Returns `false` without advancing the input if the next tokens are not the
+/// closing tag.
+fn parse_closing_tag(
+    input: ParseStream,
+    slot: bool,
+    name: &syn::Ident,
+) -> bool {
+    let parse = |input: ParseStream| -> syn::Result<bool> {
+        input.parse::<syn::Token![<]>()?;
+        input.parse::<syn::Token![/]>()?;
+        if slot {
+            input.parse::<kw::slot>()?;
+            input.parse::<syn::Token![:]>()?;
+        }
+        let closing_name = input.call(syn::Ident::parse_any)?;
+        input.parse::<syn::Token![>]>()?;
+        Ok(closing_name == *name)
+    };
+
+    let fork = input.fork();
+    if parse(&fork).unwrap_or(false) {
+        input.advance_to(&fork);
+        true
+    } else {
+        false
+    }
+}
+
+fn unclosed_tag_error(slot: bool, name: &syn::Ident) -> syn::Error {
+    syn::Error::new(
+        name.span(),
+        format_args!(
+            "opening tag has no corresponding closing </{}{}> tag",
+            if slot { "slot:" } else { "" },
+            name
+        ),
+    )
+}
+
+impl<V: Parse> Parse for ast::NamedSlot<V> {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        input.parse::<syn::Token![<]>()?;
+        input.parse::<kw::slot>()?;
+        input.parse::<syn::Token![:]>()?;
+        let name = input.call(syn::Ident::parse_any)?;
+        input.parse::<syn::Token![>]>()?;
+
+        let mut nodes = Vec::new();
+        let mut errors = Errors::default();
+        while !parse_closing_tag(input, true, &name) {
+            if input.is_empty() {
+                errors.push(unclosed_tag_error(true, &name));
+                break;
             }
+            nodes.extend(parse_or_recover(input, &mut errors));
         }
+        errors.finish()?;
 
-        Ok(Self { fmt, args })
+        Ok(Self { name, nodes })
+    }
+}
+
+/// Parses the children of a component until its closing tag, `</Name>`, or
+/// the children of a function call until the end of the input.
+fn parse_children<V: Parse>(
+    input: ParseStream,
+    name: Option<&syn::Ident>,
+) -> syn::Result<ast::Children<V>> {
+    let mut nodes = Vec::new();
+    let mut slots = Vec::new();
+    let mut errors = Errors::default();
+
+    loop {
+        match name {
+            Some(name) if parse_closing_tag(input, false, name) => break,
+            Some(name) if input.is_empty() => {
+                errors.push(unclosed_tag_error(false, name));
+                break;
+            }
+            None if input.is_empty() => break,
+            _ => {}
+        }
+
+        if input.peek(syn::Token![<])
+            && input.peek2(kw::slot)
+            && input.peek3(syn::Token![:])
+        {
+            slots.extend(parse_or_recover(input, &mut errors));
+        } else {
+            nodes.extend(parse_or_recover(input, &mut errors));
+        }
+    }
+    errors.finish()?;
+
+    Ok(ast::Children { nodes, slots })
+}
+
+impl<V: Parse> Parse for ast::Call<V> {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        input.parse::<syn::Token![@]>()?;
+        let func = input.parse()?;
+
+        let content;
+        syn::parenthesized!(content in input);
+        let args = Punctuated::parse_terminated(&content)?;
+
+        let mut children = None;
+        if input.peek(syn::token::Brace) {
+            let content;
+            syn::braced!(content in input);
+            children = Some(parse_children(&content, None)?);
+        }
+
+        Ok(Self {
+            func,
+            args,
+            children,
+        })
+    }
+}
+
+/// Parses a template without analyzing it, returning its options.
+///
+/// Layouts are not resolved, see `layout::resolve`.
+pub(crate) fn parse_unresolved(
+    input: ParseStream,
+) -> syn::Result<(Options, ast::Template<ast::ArgValue>)> {
+    let options = input.parse::<Options>()?;
+
+    let mut template = if input.peek(syn::Token![@]) && input.peek2(kw::extends)
+    {
+        ast::Template::Extends(input.parse()?)
+    } else {
+        ast::Template::Nodes(parse_nodes(input)?)
+    };
+    options.apply(template.nodes_mut());
+
+    Ok((options, template))
+}
+
+/// Parses a template, the input of `fhtml::format_args!`, and resolves its
+/// layout.
+///
+/// The resolved nodes are analyzed at once, so that elements are checked in
+/// the context of the layout they are written into.
+pub(crate) fn parse_template(
+    input: ParseStream,
+) -> syn::Result<Vec<ast::Node<ast::ArgValue>>> {
+    let (options, template) = parse_unresolved(input)?;
+    // Errors in layouts are reported at the path of the layout.
+    let span = match &template {
+        ast::Template::Extends(extends) => extends.path.span(),
+        ast::Template::Nodes(_) => proc_macro2::Span::call_site(),
+    };
+
+    include::with_files(span, |files| {
+        let nodes = layout::resolve(template.clone(), files)?;
+        analyze_template(&nodes, &options)?;
+        Ok(nodes)
+    })
+}
+
+impl Parse for FormatArgsInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let nodes = parse_template(input)?;
+
+        Ok(Self { nodes })
+    }
+}
+
+#[cfg(feature = "std")]
+impl Parse for crate::WriteIoInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let dst = input.parse()?;
+        input.parse::<syn::Token![,]>()?;
+        let nodes = parse_template(input)?;
+        let segments = lower_ast::lower_segments(nodes);
+
+        Ok(Self { dst, segments })
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Parse for crate::WriteAsyncInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let dst = input.parse()?;
+        input.parse::<syn::Token![,]>()?;
+        let nodes = parse_template(input)?;
+        let chunks = lower_ast::lower_chunks(nodes);
+
+        Ok(Self { dst, chunks })
+    }
+}
+
+impl Parse for IncludeInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let path = input.parse()?;
+
+        let mut bindings = Vec::new();
+        while !input.is_empty() {
+            input.parse::<syn::Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            let name = input.parse()?;
+            input.parse::<syn::Token![=]>()?;
+            bindings.push((name, input.parse()?));
+        }
+
+        Ok(Self { path, bindings })
     }
 }
 
@@ -174,14 +764,16 @@ impl Parse for ConcatInput {
     fn parse(input: ParseStream) -> syn::Result<Self> {
         let mut segments = Vec::new();
         let mut acc = String::new();
-        let mut nodes = Vec::new();
-
-        while !input.is_empty() {
-            let node = input.parse::<ast::Node<ast::LitValue>>()?;
-            nodes.push(node);
+        let options = input.parse::<Options>()?;
+        if input.peek(syn::Token![@]) && input.peek2(kw::extends) {
+            return Err(
+                input.error("layouts are not supported in `fhtml::concat!`")
+            );
         }
+        let mut nodes = parse_nodes::<ast::LitValue>(input)?;
+        options.apply(&mut nodes);
 
-        analyze_nodes(&nodes)?;
+        analyze_nodes(&nodes, &options)?;
 
         for node in nodes {
             for token in node.into_node_tokens() {
@@ -196,6 +788,66 @@ impl Parse for ConcatInput {
                             acc.clear();
                         }
                     }
+                    lower_ast::NodeToken::BoolAttr(name, value) => {
+                        match value {
+                            ast::LitValue::Expr(syn::Expr::Lit(
+                                syn::ExprLit {
+                                    lit: syn::Lit::Bool(lit),
+                                    ..
+                                },
+                            )) => {
+                                if lit.value {
+                                    let _ = write!(acc, " {}", name);
+                                }
+                            }
+                            value => {
+                                return Err(syn::Error::new_spanned(
+                                    value,
+                                    "expected a boolean literal",
+                                ));
+                            }
+                        }
+                    }
+                    lower_ast::NodeToken::ClassList(_, list) => {
+                        return Err(syn::Error::new(
+                            list.bracket.span.join(),
+                            "class lists with conditions or expressions are \
+                             not supported in `fhtml::concat!`",
+                        ));
+                    }
+                    lower_ast::NodeToken::OptionalAttr(name, _) => {
+                        return Err(syn::Error::new(
+                            name.span(),
+                            "optional attributes are not supported in \
+                             `fhtml::concat!`",
+                        ));
+                    }
+                    lower_ast::NodeToken::Spread(spreads, _) => {
+                        return Err(syn::Error::new(
+                            spreads[0].span(),
+                            "attribute spreading is not supported in \
+                             `fhtml::concat!`",
+                        ));
+                    }
+                    lower_ast::NodeToken::Component(component) => {
+                        return Err(syn::Error::new(
+                            component.name.span(),
+                            "components are not supported in `fhtml::concat!`",
+                        ));
+                    }
+                    lower_ast::NodeToken::Call(call) => {
+                        return Err(syn::Error::new_spanned(
+                            call.func,
+                            "function calls are not supported in \
+                             `fhtml::concat!`",
+                        ));
+                    }
+                    lower_ast::NodeToken::ControlFlow(control_flow) => {
+                        return Err(syn::Error::new(
+                            control_flow.span(),
+                            "control flow is not supported in `fhtml::concat!`",
+                        ));
+                    }
                     _ => {
                         let _ = write!(acc, "{}", token);
                     }
@@ -210,3 +862,170 @@ impl Parse for ConcatInput {
         Ok(Self { segments })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comments() {
+        syn::parse2::<ast::Comment>(quote::quote!(<!-- " foo - bar " -->))
+            .expect("a comment should be allowed");
+        syn::parse2::<ast::Comment>(
+            quote::quote!(<!--"[if IE]><p></p><![endif]"-->),
+        )
+        .expect("a conditional comment should be allowed");
+        syn::parse2::<ast::Comment>(quote::quote!(<!-- "foo -- bar" -->))
+            .expect_err("a comment containing `--` should be disallowed");
+        syn::parse2::<ast::Comment>(quote::quote!(<!-- "->" -->))
+            .expect_err("a comment starting with `->` should be disallowed");
+    }
+
+    #[test]
+    fn components() {
+        let node = syn::parse2::<ast::Node<ast::LitValue>>(quote::quote! {
+            <Card title="x"><Card><p></p></Card>"foo"</Card>
+        })
+        .unwrap();
+        let ast::Node::Component(component) = node else {
+            panic!("expected a component");
+        };
+        assert_eq!(component.name, "Card");
+        assert_eq!(component.attrs.len(), 1);
+        assert!(matches!(
+            component.children.nodes.as_slice(),
+            [
+                ast::Node::Component(_),
+                ast::Node::Value(ast::LitValue::LitStr(_))
+            ]
+        ));
+
+        let node = syn::parse2::<ast::Node<ast::LitValue>>(quote::quote! {
+            <Card />
+        })
+        .unwrap();
+        assert!(matches!(
+            node,
+            ast::Node::Component(component)
+                if component.children.nodes.is_empty()
+        ));
+
+        syn::parse2::<ast::Node<ast::LitValue>>(quote::quote! {
+            <Card><p></p>
+        })
+        .expect_err("a component without a closing tag should be disallowed");
+        syn::parse2::<ast::Node<ast::LitValue>>(quote::quote! {
+            <My-Card></My-Card>
+        })
+        .expect_err("a tag name with dashes should not be a component");
+
+        for tag in [
+            quote::quote!(<DIV>),
+            quote::quote!(<Svg>),
+            quote::quote!(<MATH>),
+        ] {
+            let node = syn::parse2::<ast::Node<ast::LitValue>>(tag).unwrap();
+            assert!(matches!(node, ast::Node::Tag(_)));
+        }
+    }
+
+    #[test]
+    fn slots() {
+        let node = syn::parse2::<ast::Node<ast::LitValue>>(quote::quote! {
+            <Card><slot:header><p></p></slot:header>"foo"<slot:footer /></Card>
+        });
+        node.expect_err("a self-closing named slot should be disallowed");
+
+        let node = syn::parse2::<ast::Node<ast::LitValue>>(quote::quote! {
+            <Card>
+                <slot:header><p></p></slot:header>
+                "foo"
+                <slot:footer></slot:footer>
+            </Card>
+        })
+        .unwrap();
+        let ast::Node::Component(component) = node else {
+            panic!("expected a component");
+        };
+        assert_eq!(component.children.nodes.len(), 1);
+        assert_eq!(component.children.slots.len(), 2);
+        assert_eq!(component.children.slots[0].name, "header");
+        assert_eq!(component.children.slots[0].nodes.len(), 2);
+
+        syn::parse2::<ast::Node<ast::LitValue>>(quote::quote! {
+            <Card><slot:header><p></p></Card>
+        })
+        .expect_err("a named slot without a closing tag should be disallowed");
+    }
+
+    #[test]
+    fn calls() {
+        let node = syn::parse2::<ast::Node<ast::LitValue>>(quote::quote! {
+            @layout::page("title", 1 + 2) {
+                <slot:header>"header"</slot:header>
+                <p></p>
+            }
+        })
+        .unwrap();
+        let ast::Node::Call(call) = node else {
+            panic!("expected a function call");
+        };
+        assert_eq!(call.args.len(), 2);
+        let children = call.children.unwrap();
+        assert_eq!(children.nodes.len(), 2);
+        assert_eq!(children.slots.len(), 1);
+
+        let node = syn::parse2::<ast::Node<ast::LitValue>>(quote::quote! {
+            @icon("close")
+        })
+        .unwrap();
+        assert!(matches!(
+            node,
+            ast::Node::Call(call) if call.children.is_none()
+        ));
+    }
+
+    #[test]
+    fn options() {
+        let options = syn::parse2::<Options>(quote::quote! {
+            #![fhtml(minify)]
+            #![fhtml(strict, lint, a11y)]
+        })
+        .unwrap();
+        assert_eq!(
+            options,
+            Options {
+                minify: true,
+                strict: true,
+                lint: true,
+                a11y: true,
+            }
+        );
+
+        assert_eq!(
+            syn::parse2::<Options>(quote::quote!()).unwrap(),
+            Options::default()
+        );
+        syn::parse2::<Options>(quote::quote!(#![fhtml(foo)]))
+            .expect_err("an unknown option should be disallowed");
+        syn::parse2::<Options>(quote::quote!(#![foo(minify)]))
+            .expect_err("an unknown attribute should be disallowed");
+    }
+
+    #[test]
+    fn recovers_from_errors() {
+        let errors = syn::parse::Parser::parse2(
+            parse_template,
+            quote::quote! {
+                <p class=>"a"</p>
+                <Card><div =>"b"</div><slot:a><i {x}></i></slot:a></Card>
+                @if x { <span =></span> } @else { "c" }
+                <b>"d"</b>
+                @for { }
+                <i =>
+            },
+        )
+        .expect_err("invalid nodes should be disallowed");
+        assert_eq!(errors.into_iter().count(), 6);
+    }
+}
@@ -0,0 +1,185 @@
+use core::fmt;
+
+use crate::escape::Context;
+use crate::render::Render;
+
+/// A reusable piece of markup, used as a custom tag in templates.
+///
+/// Tags whose name is in `PascalCase`, e.g. `<Card>`, are components, while
+/// uppercase names like `<DIV>` and the foreign elements `<Svg>` and `<Math>`
+/// are HTML elements. The attributes of the tag are the fields of the struct,
+/// and the nodes in between the opening and closing tags are passed as the
+/// `children` field, a [`Slot`]. A bare attribute is `true`, and `..{expr}`
+/// fills the remaining fields like struct update syntax, e.g.
+/// `..{Default::default()}`.
+///
+/// The output of a component is trusted markup, so it is not escaped.
+///
+/// Functions returning markup are not written as tags, but called with
+/// `@path(args)`, where a trailing block is passed as a [`Slots`] argument.
+///
+/// ```
+/// use std::fmt;
+///
+/// struct Card<'a> {
+///     title: &'a str,
+///     children: fhtml::Slot<'a>,
+/// }
+///
+/// impl fhtml::Component for Card<'_> {
+///     fn render(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+///         fhtml::write! { f,
+///             <div class="card">
+///                 <h2>{self.title}</h2>
+///                 {self.children}
+///             </div>
+///         }
+///     }
+/// }
+///
+/// let title = "<Hello>";
+/// assert_eq!(
+///     fhtml::format!(<Card title={title}><p>"World"</p></Card>),
+///     "<div class=\"card\"><h2>&lt;Hello&gt;</h2><p>World</p></div>"
+/// );
+/// ```
+pub trait Component {
+    /// Renders the component into the given formatter.
+    fn render(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result;
+}
+
+impl<T: Component + ?Sized> Component for &T {
+    #[inline]
+    fn render(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).render(f)
+    }
+}
+
+/// A lazily rendered block of template nodes, such as the children of a
+/// [`Component`] or a named slot, `<slot:name>...</slot:name>`.
+///
+/// The nodes are written directly into the formatter when the slot is
+/// rendered, without building an intermediate `String`. An empty slot can be
+/// created with [`Slot::default`].
+#[derive(Clone, Copy)]
+pub struct Slot<'a>(&'a dyn Fn(&mut fmt::Formatter<'_>) -> fmt::Result);
+
+impl<'a> Slot<'a> {
+    /// Creates a slot that renders by calling `f`.
+    #[inline]
+    pub fn new(f: &'a dyn Fn(&mut fmt::Formatter<'_>) -> fmt::Result) -> Self {
+        Self(f)
+    }
+}
+
+impl Default for Slot<'_> {
+    #[inline]
+    fn default() -> Self {
+        fn empty(_: &mut fmt::Formatter<'_>) -> fmt::Result {
+            Ok(())
+        }
+        Self(&empty)
+    }
+}
+
+impl fmt::Debug for Slot<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Slot").finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for Slot<'_> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (self.0)(f)
+    }
+}
+
+impl Render for Slot<'_> {
+    #[inline]
+    fn render(&self, f: &mut fmt::Formatter<'_>, ctx: Context) -> fmt::Result {
+        crate::__private::render_markup(self, f, ctx)
+    }
+}
+
+/// The children and named slots passed to a function called from a template.
+///
+/// A function call with a block, `@path(args) { ... }`, passes the nodes in
+/// the block as a trailing `Slots` argument. Named slots are written as
+/// `<slot:name>...</slot:name>` within the block, and the remaining nodes are
+/// the children. `Slots` renders the children, so functions that only accept
+/// children can take any [`Display`] or [`Render`] value instead.
+///
+/// The value returned by the function is rendered through [`Render`], like
+/// any other embedded expression. Returning [`fhtml::html!`] writes the
+/// markup of the function, including its slots, straight into the outer
+/// formatter.
+///
+/// ```
+/// use fhtml::{Render, Slots};
+///
+/// fn layout<'a>(title: &'a str, slots: Slots<'a>) -> impl Render + 'a {
+///     fhtml::html! {
+///         <header><h1>{title}</h1>{slots.get("header")}</header>
+///         <main>{slots}</main>
+///     }
+/// }
+///
+/// assert_eq!(
+///     fhtml::format! {
+///         @layout("Home") {
+///             <slot:header><nav>"Menu"</nav></slot:header>
+///             <p>"Welcome"</p>
+///         }
+///     },
+///     "<header><h1>Home</h1><nav>Menu</nav></header>\
+///      <main><p>Welcome</p></main>"
+/// );
+/// ```
+///
+/// [`Display`]: fmt::Display
+/// [`fhtml::html!`]: crate::html
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Slots<'a> {
+    children: Slot<'a>,
+    named: &'a [(&'static str, Slot<'a>)],
+}
+
+impl<'a> Slots<'a> {
+    /// Creates slots from the children and named slots.
+    #[inline]
+    pub fn new(
+        children: Slot<'a>,
+        named: &'a [(&'static str, Slot<'a>)],
+    ) -> Self {
+        Self { children, named }
+    }
+
+    /// Returns the children, i.e. the nodes outside of named slots.
+    #[inline]
+    pub fn children(&self) -> Slot<'a> {
+        self.children
+    }
+
+    /// Returns the named slot with the given name, if it was passed.
+    pub fn get(&self, name: &str) -> Option<Slot<'a>> {
+        self.named
+            .iter()
+            .find(|(slot_name, _)| *slot_name == name)
+            .map(|(_, slot)| *slot)
+    }
+}
+
+impl fmt::Display for Slots<'_> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.children.fmt(f)
+    }
+}
+
+impl Render for Slots<'_> {
+    #[inline]
+    fn render(&self, f: &mut fmt::Formatter<'_>, ctx: Context) -> fmt::Result {
+        self.children.render(f, ctx)
+    }
+}
@@ -0,0 +1,629 @@
+use core::fmt::{self, Write};
+
+/// The number of bytes that are checked at once for special characters.
+const CHUNK: usize = 8;
+
+/// Returns the escaped entity for a byte, if it is a special HTML character.
+#[inline]
+fn entity(byte: u8) -> Option<&'static str> {
+    match byte {
+        b'&' => Some("&amp;"),
+        b'<' => Some("&lt;"),
+        b'>' => Some("&gt;"),
+        b'"' => Some("&quot;"),
+        b'\'' => Some("&#39;"),
+        _ => None,
+    }
+}
+
+/// Special characters escaped by [`escape_into`].
+const ALL: &[u8] = b"&<>\"'";
+/// Special characters escaped in text content.
+const TEXT: &[u8] = b"&<>";
+/// Special characters escaped in (double-quoted) attribute values.
+const ATTR: &[u8] = b"&\"'";
+
+/// URL schemes that are replaced with [`BLOCKED_URL`] in URL attributes.
+const BLOCKED_SCHEMES: &[&[u8]] = &[b"javascript", b"vbscript", b"data"];
+/// The value written in place of a URL with a blocked scheme.
+const BLOCKED_URL: &str = "about:invalid";
+
+/// Returns `true` if any byte in the chunk is one of `specials`.
+#[inline]
+fn has_special(chunk: [u8; CHUNK], specials: &[u8]) -> bool {
+    const LO: u64 = u64::from_ne_bytes([0x01; CHUNK]);
+    const HI: u64 = u64::from_ne_bytes([0x80; CHUNK]);
+
+    // A byte in `x` is zero if `x - LO` borrows into its high bit, while the
+    // byte itself did not have the high bit set.
+    #[inline]
+    fn has_zero(x: u64) -> bool {
+        x.wrapping_sub(LO) & !x & HI != 0
+    }
+
+    let word = u64::from_ne_bytes(chunk);
+    specials
+        .iter()
+        .any(|&b| has_zero(word ^ u64::from_ne_bytes([b; CHUNK])))
+}
+
+/// Writes `input` to `w`, escaping the characters in `specials`.
+fn escape_with<W: Write + ?Sized>(
+    w: &mut W,
+    input: &str,
+    specials: &[u8],
+) -> fmt::Result {
+    let bytes = input.as_bytes();
+    let mut start = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if let Some(chunk) = bytes.get(i..i + CHUNK) {
+            let chunk = chunk.try_into().unwrap();
+            if !has_special(chunk, specials) {
+                i += CHUNK;
+                continue;
+            }
+        }
+
+        if specials.contains(&bytes[i]) {
+            // Special characters are ASCII, so `i` is always on a char
+            // boundary.
+            w.write_str(&input[start..i])?;
+            w.write_str(entity(bytes[i]).unwrap())?;
+            start = i + 1;
+        }
+        i += 1;
+    }
+
+    w.write_str(&input[start..])
+}
+
+/// Writes `input` to `w`, escaping special HTML characters.
+///
+/// Unlike [`escape`], this does not allocate. Runs of characters that need no
+/// escaping are written as a whole.
+///
+/// ```
+/// let mut output = String::new();
+/// fhtml::escape_into(&mut output, "<a> & <b>").unwrap();
+/// assert_eq!(output, "&lt;a&gt; &amp; &lt;b&gt;");
+/// ```
+///
+/// [`escape`]: crate::escape
+#[inline]
+pub fn escape_into<W: Write + ?Sized>(w: &mut W, input: &str) -> fmt::Result {
+    escape_with(w, input, ALL)
+}
+
+/// The context a value is rendered in, which determines how it is escaped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Context {
+    /// Text content, where `&`, `<` and `>` are escaped.
+    Text,
+    /// An attribute value, where `&` and quotes are escaped.
+    Attr,
+    /// An attribute value containing a URL, such as `href` or `src`.
+    ///
+    /// This is escaped like [`Context::Attr`], and URLs with a
+    /// `javascript:`, `vbscript:` or `data:` scheme are replaced with
+    /// `about:invalid`.
+    Url,
+    /// An attribute value containing a list of image candidates, i.e. `srcset`
+    /// or `imagesrcset`.
+    ///
+    /// Every URL in the list is blocked like in [`Context::Url`].
+    Srcset,
+    /// An attribute value containing an HTML document, i.e. `srcdoc`.
+    ///
+    /// The value is escaped like [`Context::Text`] and then like
+    /// [`Context::Attr`], so it is displayed as text in the document rather
+    /// than parsed as markup.
+    Srcdoc,
+}
+
+impl Context {
+    /// Returns the context for the value of the attribute with the given name,
+    /// ignoring case.
+    ///
+    /// The macros use this for every attribute value, so it is evaluated at
+    /// compile time for attributes written in a template.
+    ///
+    /// ```
+    /// use fhtml::Context;
+    ///
+    /// assert_eq!(Context::for_attr("HREF"), Context::Url);
+    /// assert_eq!(Context::for_attr("srcset"), Context::Srcset);
+    /// assert_eq!(Context::for_attr("title"), Context::Attr);
+    /// ```
+    pub const fn for_attr(name: &str) -> Self {
+        const URL_ATTRS: &[&str] =
+            &["action", "cite", "formaction", "href", "poster", "src"];
+        const SRCSET_ATTRS: &[&str] = &["imagesrcset", "srcset"];
+
+        if contains_ignore_case(URL_ATTRS, name) {
+            Self::Url
+        } else if contains_ignore_case(SRCSET_ATTRS, name) {
+            Self::Srcset
+        } else if eq_ignore_case("srcdoc", name) {
+            Self::Srcdoc
+        } else {
+            Self::Attr
+        }
+    }
+
+    /// Writes `input` to `w`, escaped for this context.
+    ///
+    /// ```
+    /// use fhtml::Context;
+    ///
+    /// let mut output = String::new();
+    /// Context::Text.write_str(&mut output, "<\"a\">").unwrap();
+    /// Context::Attr.write_str(&mut output, "<\"a\">").unwrap();
+    /// Context::Url.write_str(&mut output, "javascript:alert(1)").unwrap();
+    /// assert_eq!(output, "&lt;\"a\"&gt;<&quot;a&quot;>about:invalid");
+    /// ```
+    pub fn write_str<W: Write + ?Sized>(
+        self,
+        w: &mut W,
+        input: &str,
+    ) -> fmt::Result {
+        match self {
+            Self::Text => escape_with(w, input, TEXT),
+            Self::Attr => escape_with(w, input, ATTR),
+            Self::Url => {
+                let mut w = UrlWriter::new(w);
+                w.write_str(input)?;
+                w.finish()
+            }
+            Self::Srcset => {
+                let mut w = SrcsetWriter::new(w);
+                w.write_str(input)?;
+                w.finish()
+            }
+            Self::Srcdoc => {
+                escape_with(&mut ContextWriter(w, ATTR), input, TEXT)
+            }
+        }
+    }
+
+    /// Writes formatted arguments to `w`, escaped for this context.
+    pub fn write_args<W: Write + ?Sized>(
+        self,
+        w: &mut W,
+        args: fmt::Arguments<'_>,
+    ) -> fmt::Result {
+        if let Some(s) = args.as_str() {
+            return self.write_str(w, s);
+        }
+        match self {
+            Self::Text => ContextWriter(w, TEXT).write_fmt(args),
+            Self::Attr => ContextWriter(w, ATTR).write_fmt(args),
+            Self::Url => {
+                let mut w = UrlWriter::new(w);
+                w.write_fmt(args)?;
+                w.finish()
+            }
+            Self::Srcset => {
+                let mut w = SrcsetWriter::new(w);
+                w.write_fmt(args)?;
+                w.finish()
+            }
+            Self::Srcdoc => {
+                ContextWriter(&mut ContextWriter(w, ATTR), TEXT).write_fmt(args)
+            }
+        }
+    }
+}
+
+const fn contains_ignore_case(names: &[&str], name: &str) -> bool {
+    let mut i = 0;
+    while i < names.len() {
+        if eq_ignore_case(names[i], name) {
+            return true;
+        }
+        i += 1;
+    }
+    false
+}
+
+/// Returns `true` if `lower`, which is lowercase, is equal to `name` ignoring
+/// case.
+const fn eq_ignore_case(lower: &str, name: &str) -> bool {
+    let (lower, name) = (lower.as_bytes(), name.as_bytes());
+    if lower.len() != name.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < lower.len() {
+        if lower[i] != name[i].to_ascii_lowercase() {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// A writer that escapes the given special characters.
+struct ContextWriter<'a, W: ?Sized>(&'a mut W, &'static [u8]);
+
+impl<W: Write + ?Sized> Write for ContextWriter<'_, W> {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        escape_with(self.0, s, self.1)
+    }
+}
+
+/// A writer that escapes an attribute value, and blocks URLs with unsafe
+/// schemes.
+///
+/// Since the value may be written in several pieces, the start of it is
+/// buffered until the scheme is known.
+struct UrlWriter<'a, W: ?Sized> {
+    inner: &'a mut W,
+    state: UrlState,
+    // The buffered start of the value, which is always ASCII.
+    buf: [u8; 64],
+    buf_len: usize,
+    // The lowercase scheme seen so far.
+    scheme: [u8; 10],
+    scheme_len: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum UrlState {
+    Pending,
+    Safe,
+    Blocked,
+}
+
+impl<'a, W: Write + ?Sized> UrlWriter<'a, W> {
+    fn new(inner: &'a mut W) -> Self {
+        Self {
+            inner,
+            state: UrlState::Pending,
+            buf: [0; 64],
+            buf_len: 0,
+            scheme: [0; 10],
+            scheme_len: 0,
+        }
+    }
+
+    fn flush_buf(&mut self) -> fmt::Result {
+        let buf = core::str::from_utf8(&self.buf[..self.buf_len]).unwrap();
+        escape_with(self.inner, buf, ATTR)
+    }
+
+    /// Ends the URL, writing its buffered start if the scheme was never
+    /// completed, so that another URL can be written.
+    fn finish(&mut self) -> fmt::Result {
+        if self.state == UrlState::Pending {
+            self.flush_buf()?;
+        }
+        self.state = UrlState::Pending;
+        self.buf_len = 0;
+        self.scheme_len = 0;
+        Ok(())
+    }
+}
+
+impl<W: Write + ?Sized> Write for UrlWriter<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let bytes = s.as_bytes();
+        let was_pending = self.state == UrlState::Pending;
+        let mut i = 0;
+
+        while self.state == UrlState::Pending && i < bytes.len() {
+            let b = bytes[i];
+            let is_scheme_char = if self.scheme_len == 0 {
+                b.is_ascii_alphabetic()
+            } else {
+                b.is_ascii_alphanumeric() || matches!(b, b'+' | b'-' | b'.')
+            };
+
+            if is_scheme_char {
+                if self.scheme_len == self.scheme.len() {
+                    // Longer than any blocked scheme.
+                    self.state = UrlState::Safe;
+                    break;
+                }
+                self.scheme[self.scheme_len] = b.to_ascii_lowercase();
+                self.scheme_len += 1;
+            } else if b == b':' {
+                let scheme = &self.scheme[..self.scheme_len];
+                self.state = if BLOCKED_SCHEMES.contains(&scheme) {
+                    UrlState::Blocked
+                } else {
+                    UrlState::Safe
+                };
+                break;
+            } else if (self.scheme_len == 0 && b <= b' ')
+                || matches!(b, b'\t' | b'\n' | b'\r')
+            {
+                // Browsers ignore leading whitespace and control characters,
+                // as well as tabs and newlines anywhere in a URL.
+            } else {
+                self.state = UrlState::Safe;
+                break;
+            }
+
+            if self.buf_len == self.buf.len() {
+                self.state = UrlState::Blocked;
+                break;
+            }
+            self.buf[self.buf_len] = b;
+            self.buf_len += 1;
+            i += 1;
+        }
+
+        match self.state {
+            UrlState::Pending => Ok(()),
+            UrlState::Safe => {
+                if was_pending {
+                    self.flush_buf()?;
+                }
+                escape_with(self.inner, &s[i..], ATTR)
+            }
+            UrlState::Blocked if was_pending => {
+                self.inner.write_str(BLOCKED_URL)
+            }
+            UrlState::Blocked => Ok(()),
+        }
+    }
+}
+
+/// A writer that escapes a `srcset` value, and blocks every URL with an unsafe
+/// scheme.
+///
+/// Image candidates are separated by commas, and each consists of a URL
+/// followed by optional descriptors after whitespace.
+struct SrcsetWriter<'a, W: ?Sized> {
+    url: UrlWriter<'a, W>,
+    state: SrcsetState,
+    // Whether the last byte of the current URL is a comma, which ends the
+    // candidate instead of starting its descriptors.
+    trailing_comma: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SrcsetState {
+    /// Between candidates, before the next URL.
+    Separator,
+    Url,
+    Descriptors,
+}
+
+impl<'a, W: Write + ?Sized> SrcsetWriter<'a, W> {
+    fn new(inner: &'a mut W) -> Self {
+        Self {
+            url: UrlWriter::new(inner),
+            state: SrcsetState::Separator,
+            trailing_comma: false,
+        }
+    }
+
+    fn finish(&mut self) -> fmt::Result {
+        if self.state == SrcsetState::Url {
+            self.url.finish()?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write + ?Sized> Write for SrcsetWriter<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let bytes = s.as_bytes();
+        let mut start = 0;
+
+        for (i, &b) in bytes.iter().enumerate() {
+            let is_space = b.is_ascii_whitespace();
+            let next = match self.state {
+                SrcsetState::Separator if !is_space && b != b',' => {
+                    SrcsetState::Url
+                }
+                SrcsetState::Url if is_space => {
+                    if self.trailing_comma {
+                        SrcsetState::Separator
+                    } else {
+                        SrcsetState::Descriptors
+                    }
+                }
+                SrcsetState::Descriptors if b == b',' => SrcsetState::Separator,
+                state => state,
+            };
+            if self.state == SrcsetState::Url {
+                self.trailing_comma = b == b',';
+            }
+            if next == self.state {
+                continue;
+            }
+
+            // Separators and URLs are split at ASCII bytes, so `i` is always
+            // on a char boundary.
+            if self.state == SrcsetState::Url {
+                self.url.write_str(&s[start..i])?;
+                self.url.finish()?;
+            } else {
+                escape_with(self.url.inner, &s[start..i], ATTR)?;
+            }
+            self.state = next;
+            self.trailing_comma = false;
+            start = i;
+        }
+
+        if self.state == SrcsetState::Url {
+            self.url.write_str(&s[start..])
+        } else {
+            escape_with(self.url.inner, &s[start..], ATTR)
+        }
+    }
+}
+
+/// A [`fmt::Display`] adapter that escapes the output of the inner value while
+/// it is being formatted.
+///
+/// This allows any [`fmt::Display`] type to be embedded in an fhtml macro
+/// without rendering it to an intermediate `String` first.
+///
+/// ```
+/// let path = std::path::Path::new("<dir>/file");
+/// assert_eq!(
+///     fhtml::format!(<p>{fhtml::Escaped(path.display())}</p>),
+///     "<p>&lt;dir&gt;/file</p>"
+/// );
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Escaped<T>(pub T);
+
+impl<T: fmt::Display> fmt::Display for Escaped<T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(EscapeWriter(f), "{}", self.0)
+    }
+}
+
+/// A writer that escapes everything written to it.
+pub(crate) struct EscapeWriter<W>(pub W);
+
+impl<W: Write> Write for EscapeWriter<W> {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        escape_into(&mut self.0, s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn escaped(input: &str) -> String {
+        let mut output = String::new();
+        escape_into(&mut output, input).unwrap();
+        output
+    }
+
+    #[test]
+    fn no_special_chars() {
+        assert_eq!(escaped(""), "");
+        assert_eq!(escaped("foo"), "foo");
+        assert_eq!(
+            escaped("a long string without any special characters"),
+            "a long string without any special characters"
+        );
+    }
+
+    #[test]
+    fn special_chars() {
+        assert_eq!(escaped("&<>\"'"), "&amp;&lt;&gt;&quot;&#39;");
+        assert_eq!(
+            escaped("0123456789abcdef<0123456789abcdef>"),
+            "0123456789abcdef&lt;0123456789abcdef&gt;"
+        );
+        assert_eq!(escaped("1234567&"), "1234567&amp;");
+        assert_eq!(escaped("12345678&"), "12345678&amp;");
+    }
+
+    #[test]
+    fn multibyte_chars() {
+        assert_eq!(escaped("åäö <ü> 😀&"), "åäö &lt;ü&gt; 😀&amp;");
+        // Bytes of multibyte characters never match special characters.
+        assert_eq!(escaped("ĀĀĀĀĀĀĀĀ"), "ĀĀĀĀĀĀĀĀ");
+    }
+
+    #[test]
+    fn contexts() {
+        let escaped = |ctx: Context, input| {
+            let mut output = String::new();
+            ctx.write_str(&mut output, input).unwrap();
+            output
+        };
+
+        assert_eq!(escaped(Context::Text, "&<>\"'"), "&amp;&lt;&gt;\"'");
+        assert_eq!(escaped(Context::Attr, "&<>\"'"), "&amp;<>&quot;&#39;");
+        assert_eq!(escaped(Context::Url, "/a?b=1&c=2"), "/a?b=1&amp;c=2");
+        assert_eq!(
+            escaped(Context::Srcdoc, "<b>\"a\" & 'b'</b>"),
+            "&amp;lt;b&amp;gt;&quot;a&quot; &amp;amp; &#39;b&#39;&amp;lt;/b&amp;gt;"
+        );
+    }
+
+    #[test]
+    fn attr_contexts() {
+        assert_eq!(Context::for_attr("title"), Context::Attr);
+        assert_eq!(Context::for_attr("data-href"), Context::Attr);
+        assert_eq!(Context::for_attr("href"), Context::Url);
+        assert_eq!(Context::for_attr("SRC"), Context::Url);
+        assert_eq!(Context::for_attr("imageSrcset"), Context::Srcset);
+        assert_eq!(Context::for_attr("srcdoc"), Context::Srcdoc);
+    }
+
+    #[test]
+    fn blocked_srcset_urls() {
+        let srcset = |pieces: &[&str]| {
+            let mut output = String::new();
+            let mut w = SrcsetWriter::new(&mut output);
+            for piece in pieces {
+                w.write_str(piece).unwrap();
+            }
+            w.finish().unwrap();
+            output
+        };
+
+        assert_eq!(
+            srcset(&["a.png 1x, b.png?x=1&y=2 2x"]),
+            "a.png 1x, b.png?x=1&amp;y=2 2x"
+        );
+        assert_eq!(
+            srcset(&["a.png 1x, javascript:alert(1) 2x"]),
+            "a.png 1x, about:invalid 2x"
+        );
+        assert_eq!(
+            srcset(&["a.png, java", "script:x"]),
+            "a.png, about:invalid"
+        );
+        assert_eq!(
+            srcset(&["data:image/png;base64,AAAA 1x"]),
+            "about:invalid 1x"
+        );
+        assert_eq!(srcset(&[" ,javascript:x"]), " ,about:invalid");
+        assert_eq!(srcset(&["a.png 100w,b.png"]), "a.png 100w,b.png");
+    }
+
+    #[test]
+    fn blocked_urls() {
+        let url = |pieces: &[&str]| {
+            let mut output = String::new();
+            let mut w = UrlWriter::new(&mut output);
+            for piece in pieces {
+                w.write_str(piece).unwrap();
+            }
+            w.finish().unwrap();
+            output
+        };
+
+        assert_eq!(url(&["javascript:alert(1)"]), "about:invalid");
+        assert_eq!(url(&["  JAVASCRIPT:alert(1)"]), "about:invalid");
+        assert_eq!(url(&["java\tscript:alert(1)"]), "about:invalid");
+        assert_eq!(url(&["java", "script", ":alert(1)"]), "about:invalid");
+        assert_eq!(url(&["vbscript:msgbox"]), "about:invalid");
+        assert_eq!(url(&["data:text/html,<b>"]), "about:invalid");
+        assert_eq!(url(&[&" ".repeat(100), "javascript:"]), "about:invalid");
+
+        assert_eq!(url(&["https://example.com"]), "https://example.com");
+        assert_eq!(url(&["mailto:", "a@b.c"]), "mailto:a@b.c");
+        assert_eq!(url(&["java", "/script:"]), "java/script:");
+        assert_eq!(url(&["javascripts:"]), "javascripts:");
+        assert_eq!(url(&["  page.html"]), "  page.html");
+        assert_eq!(url(&["#top"]), "#top");
+        assert_eq!(url(&[""]), "");
+    }
+
+    #[test]
+    fn escaped_display() {
+        assert_eq!(
+            Escaped(format_args!("{}>{}", "<a", 1)).to_string(),
+            "&lt;a&gt;1"
+        );
+    }
+}
@@ -22,10 +22,734 @@
 //! [`std::write!`]: https://doc.rust-lang.org/stable/std/macro.write.html
 //! [`std::writeln!`]: https://doc.rust-lang.org/stable/std/macro.writeln.html
 //!
-//! Because of the absence of custom traits, fhtml does not provide automatic
-//! escaping for values.
+//! # Escaping
+//!
+//! Every embedded expression `{expr}` is rendered through the [`Render`]
+//! trait, which escapes special HTML characters for text-like types such as
+//! `&str` and `String`. String literals written directly in the template are
+//! trusted and inserted as-is, and so are values wrapped in [`PreEscaped`],
+//! e.g. using [`raw`].
+//!
+//! Values are escaped according to the [`Context`] they appear in, e.g. text
+//! content or an attribute value. URL attributes such as `href` and `srcset`
+//! block unsafe schemes like `javascript:`, `srcdoc` is escaped as a document
+//! in an attribute, and event handler attributes such as `onclick` only accept
+//! string literals.
+//!
+//! ```
+//! let name = "<script>";
+//! assert_eq!(
+//!     fhtml::format!(<p>{name}</p>),
+//!     "<p>&lt;script&gt;</p>"
+//! );
+//! ```
+
+//! # Control flow
+//!
+//! Templates support `@if`, `@else`, `@for`, `@match` and `@let`, which are
+//! written directly into the formatter.
+//!
+//! ```
+//! let items = ["a", "b"];
+//! assert_eq!(
+//!     fhtml::format! {
+//!         <ul>
+//!             @for item in &items {
+//!                 @if *item == "a" { <li class="first">{item}</li> }
+//!                 @else { <li>{item}</li> }
+//!             }
+//!         </ul>
+//!     },
+//!     "<ul><li class=\"first\">a</li><li>b</li></ul>"
+//! );
+//! ```
+
+//! # Spread attributes
+//!
+//! `..{attrs}` writes the `(name, value)` pairs of a collection or iterator
+//! as attributes. Since the names are only known at runtime, pairs are
+//! skipped without an error when their name is written on the tag itself,
+//! repeats an earlier pair, is not a valid attribute name, or is an event
+//! handler (`on*`). Names are compared case-insensitively, so the attribute
+//! on the tag wins over any pair, and the first pair wins over later ones.
+//! Tags with both attributes and spreads are warned about, as a `deprecated`
+//! warning that can be allowed with `#[allow(deprecated)]`.
+//!
+//! ```
+//! # #![allow(deprecated)]
+//! let attrs = [("ID", "b"), ("title", "x"), ("title", "y"), ("onclick", "f()")];
+//! assert_eq!(
+//!     fhtml::format!(<p id="a" ..{attrs}></p>),
+//!     "<p id=\"a\" title=\"x\"></p>"
+//! );
+//! ```
+
+//! # Lazy markup
+//!
+//! [`fhtml::html!`] creates an [`Html`] value that is rendered when it is
+//! displayed, rather than when it is created. It owns the values it embeds,
+//! so helper functions can return markup fragments that are written straight
+//! into the buffer of the template they end up in. In attribute values, the
+//! markup is escaped like any other value.
+//!
+//! ```
+//! fn badge(count: u32) -> impl fhtml::Render {
+//!     fhtml::html!(<span class="badge">{count}</span>)
+//! }
+//!
+//! assert_eq!(
+//!     fhtml::format!(<p>"Inbox "{badge(3)}</p>),
+//!     "<p>Inbox <span class=\"badge\">3</span></p>"
+//! );
+//! ```
+//!
+//! [`fhtml::html!`]: crate::html
 
+//! # Writing bytes
+//!
+//! [`fhtml::write_io!`] writes to an [`io::Write`] and returns an
+//! `io::Result<()>`. Static markup is written as byte slices, and embedded
+//! expressions are escaped as usual. Every segment is a separate write, so
+//! files and sockets should be wrapped in a [`BufWriter`].
+//!
+//! ```
+//! let mut page = Vec::new();
+//! let name = "<Bob>";
+//! fhtml::write_io!(page, <h1>"Hello, "{name}</h1>)?;
+//! assert_eq!(page, b"<h1>Hello, &lt;Bob&gt;</h1>");
+//! # Ok::<(), std::io::Error>(())
+//! ```
+//!
+//! With the `tokio` feature, `fhtml::write_async!` writes to a
+//! `tokio::io::AsyncWrite`, flushing after each top-level element and each
+//! iteration of a top-level `@for`, so the start of a large document is sent
+//! while the rest is rendered.
+//!
+//! [`fhtml::write_io!`]: crate::write_io
+//! [`io::Write`]: std::io::Write
+//! [`BufWriter`]: std::io::BufWriter
+
+//! # Comments
+//!
+//! Comments are written as `<!-- "text" -->`, where the text is inserted
+//! as-is. They can be stripped from the output with the `minify` option, set
+//! by an inner attribute at the start of the macro input.
+//!
+//! ```
+//! assert_eq!(
+//!     fhtml::format!(<!-- " greeting " --> <p>"Hello"</p>),
+//!     "<!-- greeting --><p>Hello</p>"
+//! );
+//! assert_eq!(
+//!     fhtml::format!(#![fhtml(minify)] <!-- "greeting" --> <p>"Hello"</p>),
+//!     "<p>Hello</p>"
+//! );
+//! ```
+
+//! # Strict mode
+//!
+//! The `strict` option checks that standard HTML elements are nested
+//! according to their content model, e.g. that `<li>` is in a list and that
+//! `<p>` contains no block elements, since browsers re-parent misnested
+//! markup.
+//!
+//! ```compile_fail
+//! fhtml::format!(#![fhtml(strict)] <table><tr><td>"a"</td></tr></table>);
+//! ```
+//!
+//! # Linting names
+//!
+//! The `lint` option checks that element and attribute names are defined by
+//! the HTML standard, and suggests the closest name for likely typos. Custom
+//! elements, whose names contain a dash, `data-*` and `aria-*` attributes, and
+//! the contents of `<svg>` and `<math>` are allowed.
+//!
+//! ```compile_fail
+//! // error: unknown attribute `clas`, did you mean `class`?
+//! fhtml::format!(#![fhtml(lint)] <p clas="intro">"Hello"</p>);
+//! ```
+//!
+//! # Accessibility checks
+//!
+//! The `a11y` option checks for common accessibility problems, e.g. images
+//! without alternative text, links without a destination, form controls
+//! without a label, and invalid WAI-ARIA roles and attributes.
+//!
+//! ```compile_fail
+//! // error: <input> must have an associated <label>, or an `aria-label` or
+//! // `aria-labelledby` attribute
+//! fhtml::format!(#![fhtml(a11y)] <input type="text" name="q">);
+//! ```
+//!
+//! ```
+//! assert_eq!(
+//!     fhtml::format!(
+//!         #![fhtml(a11y)]
+//!         <label for="q">"Search"</label><input id="q" type="text">
+//!     ),
+//!     "<label for=\"q\">Search</label><input id=\"q\" type=\"text\">"
+//! );
+//! ```
+//!
+//! # Deriving `Render`
+//!
+//! [`Render`] and `Display` can be derived for a type with a template in the
+//! `#[template(...)]` attribute, where `self` and the named fields of a struct
+//! are in scope. The output is trusted markup, so it is not escaped when the
+//! value is embedded as text in another template, while attribute values are
+//! escaped as usual.
+//!
+//! ```
+//! #[derive(fhtml::Render)]
+//! #[template(<li class=["done" => *done]>{name}</li>)]
+//! struct Item {
+//!     name: String,
+//!     done: bool,
+//! }
+//!
+//! let item = Item { name: "<Milk>".to_owned(), done: true };
+//! assert_eq!(
+//!     fhtml::format!(<ul>{item}</ul>),
+//!     "<ul><li class=\"done\">&lt;Milk&gt;</li></ul>"
+//! );
+//! ```
+
+//! # Template files
+//!
+//! Large templates can be written in a separate file and included at compile
+//! time with [`fhtml::include!`], which returns an [`fmt::Arguments`] like
+//! [`fhtml::format_args!`]. The path is relative to `CARGO_MANIFEST_DIR`, and
+//! values can be bound for the template as `name = value`.
+//!
+//! ```ignore
+//! let page = std::fmt::format(fhtml::include!(
+//!     "templates/page.fhtml",
+//!     title = "Home",
+//!     items = &items,
+//! ));
+//! ```
+//!
+//! A template can extend a layout file with `@extends "path"`, followed by
+//! the `@block name { ... }`s that replace the blocks of the same name in the
+//! layout. Blocks that are not replaced keep their content from the layout,
+//! and layouts can extend other layouts.
+//!
+//! ```ignore
+//! let page = fhtml::format! {
+//!     @extends "templates/base.fhtml"
+//!     @block title { "Home" }
+//!     @block content { <p>"Welcome"</p> }
+//! };
+//! ```
+//!
+//! [`fhtml::include!`]: https://docs.rs/fhtml/latest/fhtml/macro.include.html
+//! [`fmt::Arguments`]: std::fmt::Arguments
+
+//! # Features
+//!
+//! - `std` (default): enables `alloc` and [`fhtml::write_io!`].
+//! - `alloc`: enables [`fhtml::format!`], [`escape`], and [`Render`] for
+//!   `String`, `Box` and other allocated types.
+//! - `tokio`: enables `fhtml::write_async!`.
+//!
+//! Without `std`, the crate is `no_std`, and the generated code only refers
+//! to `core`, so [`fhtml::write!`] and [`fhtml::format_args!`] work with any
+//! [`core::fmt::Write`].
+//!
+//! [`fhtml::write_io!`]: crate::write_io
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+// Allows the generated code to refer to `::fhtml` from within this crate.
+extern crate self as fhtml;
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+mod component;
+mod escape;
+mod render;
+
+pub use component::{Component, Slot, Slots};
+pub use escape::{escape_into, Context, Escaped};
 pub use fhtml_macros::*;
+pub use render::{raw, Html, PreEscaped, Render};
+
+#[doc(hidden)]
+pub mod __private {
+    use core::cell::Cell;
+    use core::fmt::{self, Write};
+    #[cfg(feature = "std")]
+    use std::io;
+
+    use crate::{Component, Context, Render};
+
+    /// Renders trusted markup, which is written as-is in text, and escaped for
+    /// any other context, so that it cannot end an attribute value or carry a
+    /// `javascript:` URL.
+    ///
+    /// Used by the markup types and the generated `Render` implementations.
+    #[inline]
+    pub fn render_markup<T: fmt::Display + ?Sized>(
+        markup: &T,
+        f: &mut fmt::Formatter<'_>,
+        ctx: Context,
+    ) -> fmt::Result {
+        match ctx {
+            Context::Text => markup.fmt(f),
+            ctx => ctx.write_args(f, format_args!("{}", markup)),
+        }
+    }
+
+    /// Adapts a [`Render`] value to [`fmt::Display`], used by the generated
+    /// code for embedded expressions.
+    pub struct Rendered<'a, T: ?Sized>(pub &'a T, pub Context);
+
+    impl<T: Render + ?Sized> fmt::Display for Rendered<'_, T> {
+        #[inline]
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.0.render(f, self.1)
+        }
+    }
+
+    /// An optional attribute, which is written if the value is `Some`.
+    pub struct OptionalAttr<'a, T: ?Sized>(
+        pub &'static str,
+        pub &'a T,
+        pub Context,
+    );
+
+    impl<T: AsOption + ?Sized> fmt::Display for OptionalAttr<'_, T> {
+        #[inline]
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self.1.as_option() {
+                Some(value) => {
+                    f.write_str(self.0)?;
+                    f.write_char('"')?;
+                    value.render(f, self.2)?;
+                    f.write_char('"')
+                }
+                None => Ok(()),
+            }
+        }
+    }
+
+    /// Values of optional attributes, i.e. `Option<T>` or references to it.
+    pub trait AsOption {
+        type Item: Render + ?Sized;
+
+        fn as_option(&self) -> Option<&Self::Item>;
+    }
+
+    impl<T: Render> AsOption for Option<T> {
+        type Item = T;
+
+        #[inline]
+        fn as_option(&self) -> Option<&T> {
+            self.as_ref()
+        }
+    }
+
+    impl<T: AsOption + ?Sized> AsOption for &T {
+        type Item = T::Item;
+
+        #[inline]
+        fn as_option(&self) -> Option<&T::Item> {
+            (**self).as_option()
+        }
+    }
+
+    /// A class list, which writes the attribute with the active class names,
+    /// if there are any.
+    pub struct ClassList<'a>(
+        pub &'static str,
+        pub &'a [Option<&'a str>],
+        pub Context,
+    );
+
+    impl ClassList<'_> {
+        /// Returns `true` if the class name at the given position was already
+        /// written.
+        fn is_duplicate(&self, item: usize, index: usize, name: &str) -> bool {
+            self.1[..=item].iter().enumerate().any(|(i, other)| {
+                let other = other.unwrap_or_default().split_ascii_whitespace();
+                let n = if i == item { index } else { usize::MAX };
+                other.take(n).any(|other| other == name)
+            })
+        }
+    }
+
+    impl fmt::Display for ClassList<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let mut is_empty = true;
+
+            for (i, item) in self.1.iter().enumerate() {
+                let names = item.unwrap_or_default().split_ascii_whitespace();
+                for (j, name) in names.enumerate() {
+                    if self.is_duplicate(i, j, name) {
+                        continue;
+                    }
+                    if is_empty {
+                        f.write_str(self.0)?;
+                        f.write_char('"')?;
+                        is_empty = false;
+                    } else {
+                        f.write_char(' ')?;
+                    }
+                    self.2.write_str(f, name)?;
+                }
+            }
+
+            if !is_empty {
+                f.write_char('"')?;
+            }
+            Ok(())
+        }
+    }
+
+    /// Values of class list items.
+    pub trait AsClass {
+        fn as_class(&self) -> Option<&str>;
+    }
+
+    impl AsClass for str {
+        #[inline]
+        fn as_class(&self) -> Option<&str> {
+            Some(self)
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    impl AsClass for alloc::string::String {
+        #[inline]
+        fn as_class(&self) -> Option<&str> {
+            Some(self)
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    impl AsClass for alloc::borrow::Cow<'_, str> {
+        #[inline]
+        fn as_class(&self) -> Option<&str> {
+            Some(self)
+        }
+    }
+
+    impl<T: AsClass> AsClass for Option<T> {
+        #[inline]
+        fn as_class(&self) -> Option<&str> {
+            self.as_ref().and_then(T::as_class)
+        }
+    }
+
+    impl<T: AsClass + ?Sized> AsClass for &T {
+        #[inline]
+        fn as_class(&self) -> Option<&str> {
+            (**self).as_class()
+        }
+    }
+
+    /// Writes the spread attributes of a tag from collections of name-value
+    /// pairs, skipping the names of the tag's other attributes. Used by the
+    /// generated code within a [`Template`] or [`TemplateOnce`].
+    ///
+    /// Pairs with invalid names, event handler names (`on*`) or names that
+    /// were already written are skipped as well.
+    #[inline]
+    pub fn write_spread(
+        f: &mut fmt::Formatter<'_>,
+        static_names: &'static [&'static str],
+        attrs: impl FnOnce(&mut SpreadWriter<'_, '_>) -> fmt::Result,
+    ) -> fmt::Result {
+        attrs(&mut SpreadWriter {
+            f,
+            static_names,
+            #[cfg(feature = "alloc")]
+            seen: alloc::collections::BTreeSet::new(),
+        })
+    }
+
+    /// Writes the spread attributes of a tag, see [`write_spread`].
+    pub struct SpreadWriter<'a, 'b> {
+        f: &'a mut fmt::Formatter<'b>,
+        static_names: &'static [&'static str],
+        /// The lowercased names of the written pairs, since attribute names
+        /// are case insensitive. Without `alloc`, only the names of the tag's
+        /// other attributes are skipped.
+        #[cfg(feature = "alloc")]
+        seen: alloc::collections::BTreeSet<alloc::string::String>,
+    }
+
+    impl SpreadWriter<'_, '_> {
+        pub fn write_attrs<I>(&mut self, attrs: I) -> fmt::Result
+        where
+            I: IntoIterator,
+            I::Item: SpreadAttr,
+        {
+            for attr in attrs {
+                let name = attr.name();
+                if !is_valid_attr_name(name)
+                    || name
+                        .get(..2)
+                        .is_some_and(|p| p.eq_ignore_ascii_case("on"))
+                    || self
+                        .static_names
+                        .iter()
+                        .any(|n| n.eq_ignore_ascii_case(name))
+                {
+                    continue;
+                }
+                #[cfg(feature = "alloc")]
+                if !self.seen.insert(name.to_ascii_lowercase()) {
+                    continue;
+                }
+                self.f.write_char(' ')?;
+                self.f.write_str(name)?;
+                self.f.write_str("=\"")?;
+                attr.value().render(self.f, Context::for_attr(name))?;
+                self.f.write_char('"')?;
+            }
+            Ok(())
+        }
+    }
+
+    /// Returns `true` if `name` can be written as an attribute name.
+    fn is_valid_attr_name(name: &str) -> bool {
+        !name.is_empty()
+            && !name.chars().any(|c| {
+                c.is_control()
+                    || c.is_whitespace()
+                    || matches!(c, '"' | '\'' | '<' | '>' | '/' | '=')
+            })
+    }
+
+    /// Name-value pairs of spread attributes.
+    pub trait SpreadAttr {
+        fn name(&self) -> &str;
+
+        fn value(&self) -> &dyn Render;
+    }
+
+    impl<K: AsRef<str>, V: Render> SpreadAttr for (K, V) {
+        #[inline]
+        fn name(&self) -> &str {
+            self.0.as_ref()
+        }
+
+        #[inline]
+        fn value(&self) -> &dyn Render {
+            &self.1
+        }
+    }
+
+    impl<T: SpreadAttr + ?Sized> SpreadAttr for &T {
+        #[inline]
+        fn name(&self) -> &str {
+            (**self).name()
+        }
+
+        #[inline]
+        fn value(&self) -> &dyn Render {
+            (**self).value()
+        }
+    }
+
+    /// Reborrows the destination of `fhtml::write_io!` as an `io::Write`,
+    /// like the method call of `std::write!`.
+    #[cfg(feature = "std")]
+    pub trait WriteIo: io::Write {
+        #[inline]
+        fn as_io_write(&mut self) -> &mut Self {
+            self
+        }
+    }
+
+    #[cfg(feature = "std")]
+    impl<W: io::Write + ?Sized> WriteIo for W {}
+
+    #[cfg(feature = "tokio")]
+    pub use tokio::io::AsyncWriteExt;
+
+    /// Reborrows the destination of `fhtml::write_async!` as an
+    /// `AsyncWrite`, see [`WriteIo`].
+    #[cfg(feature = "tokio")]
+    pub trait AsyncWriteDst: tokio::io::AsyncWrite + Unpin {
+        #[inline]
+        fn as_async_write(&mut self) -> &mut Self {
+            self
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    impl<W: tokio::io::AsyncWrite + Unpin + ?Sized> AsyncWriteDst for W {}
+
+    /// Formats a chunk of `fhtml::write_async!` into `buf`, replacing its
+    /// previous contents.
+    #[cfg(feature = "tokio")]
+    pub fn format_chunk(
+        buf: &mut String,
+        args: fmt::Arguments<'_>,
+    ) -> io::Result<()> {
+        buf.clear();
+        buf.write_fmt(args).map_err(|_| {
+            io::Error::new(io::ErrorKind::Other, "formatter error")
+        })
+    }
+
+    /// Formats `args` into a `String` with the given initial capacity, used
+    /// by `fhtml::format!`.
+    #[cfg(feature = "alloc")]
+    pub fn format(
+        capacity: usize,
+        args: fmt::Arguments<'_>,
+    ) -> alloc::string::String {
+        // Like `alloc::fmt::format`, templates without arguments are copied.
+        if let Some(s) = args.as_str() {
+            return s.into();
+        }
+        let mut output = alloc::string::String::with_capacity(capacity);
+        output
+            .write_fmt(args)
+            .expect("a formatting trait implementation returned an error");
+        output
+    }
+
+    /// The estimated length of a formatting argument of the generated code,
+    /// see [`Render::size_hint`].
+    pub trait SizeHint {
+        fn size_hint(&self) -> usize;
+    }
+
+    impl<T: Render + ?Sized> SizeHint for Rendered<'_, T> {
+        #[inline]
+        fn size_hint(&self) -> usize {
+            self.0.size_hint()
+        }
+    }
+
+    impl<T: AsOption + ?Sized> SizeHint for OptionalAttr<'_, T> {
+        #[inline]
+        fn size_hint(&self) -> usize {
+            self.1
+                .as_option()
+                .map_or(0, |value| self.0.len() + 2 + value.size_hint())
+        }
+    }
+
+    impl SizeHint for ClassList<'_> {
+        #[inline]
+        fn size_hint(&self) -> usize {
+            // Each name is followed by a space, except the last one, and the
+            // value is quoted.
+            let names = self.1.iter().flatten().map(|name| name.len() + 1);
+            match names.sum::<usize>() {
+                0 => 0,
+                len => self.0.len() + 1 + len,
+            }
+        }
+    }
+
+    impl SizeHint for &str {
+        #[inline]
+        fn size_hint(&self) -> usize {
+            self.len()
+        }
+    }
+
+    // Spreads, components and control flow are rendered lazily, so their
+    // length is unknown.
+    impl<T: ?Sized> SizeHint for RenderComponent<'_, T> {
+        #[inline]
+        fn size_hint(&self) -> usize {
+            0
+        }
+    }
+
+    impl<F> SizeHint for Template<F> {
+        #[inline]
+        fn size_hint(&self) -> usize {
+            0
+        }
+    }
+
+    impl<F> SizeHint for TemplateOnce<F> {
+        #[inline]
+        fn size_hint(&self) -> usize {
+            0
+        }
+    }
+
+    /// Adapts a [`Component`] to [`fmt::Display`], used by the generated code
+    /// for components.
+    pub struct RenderComponent<'a, T: ?Sized>(pub &'a T);
+
+    impl<T: Component + ?Sized> fmt::Display for RenderComponent<'_, T> {
+        #[inline]
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.0.render(f)
+        }
+    }
+
+    /// A closure writing into a formatter, used by the generated code for
+    /// control flow and spread attributes.
+    ///
+    /// The closure can run any number of times, since the `fmt::Arguments`
+    /// of `fhtml::format_args!` can be copied and displayed more than once.
+    pub struct Template<F>(F);
+
+    impl<F> Template<F>
+    where
+        F: Fn(&mut fmt::Formatter<'_>) -> fmt::Result,
+    {
+        #[inline]
+        pub fn new(f: F) -> Self {
+            Self(f)
+        }
+    }
+
+    impl<F> fmt::Display for Template<F>
+    where
+        F: Fn(&mut fmt::Formatter<'_>) -> fmt::Result,
+    {
+        #[inline]
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            (self.0)(f)
+        }
+    }
+
+    /// A [`Template`] whose closure runs once, so it can move the values it
+    /// iterates over.
+    ///
+    /// Only used where the generated code displays the template once, i.e. by
+    /// the macros that format their output right away, and within other
+    /// templates, which create their templates each time they run.
+    pub struct TemplateOnce<F>(Cell<Option<F>>);
+
+    impl<F> TemplateOnce<F>
+    where
+        F: FnOnce(&mut fmt::Formatter<'_>) -> fmt::Result,
+    {
+        #[inline]
+        pub fn new(f: F) -> Self {
+            Self(Cell::new(Some(f)))
+        }
+    }
+
+    impl<F> fmt::Display for TemplateOnce<F>
+    where
+        F: FnOnce(&mut fmt::Formatter<'_>) -> fmt::Result,
+    {
+        #[inline]
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self.0.take() {
+                Some(template) => template(f),
+                // The values the closure moved are gone, which is only
+                // possible with a writer formatting its arguments twice.
+                None => Err(fmt::Error),
+            }
+        }
+    }
+}
 
 /// Writes formatted HTML to a buffer.
 ///
@@ -58,30 +782,63 @@ macro_rules! writeln {
 ///
 /// See [`std::format!`] for more information.
 ///
+/// Requires the `alloc` feature.
+///
 /// [`std::format!`]: https://doc.rust-lang.org/stable/std/macro.format.html
+#[cfg(feature = "alloc")]
 #[macro_export]
 macro_rules! format {
     ($($arg:tt)*) => {{
-        let res = ::std::fmt::format($crate::format_args!($($arg)*));
+        let res = $crate::__format!($($arg)*);
         res
     }};
 }
 
+/// Creates lazily rendered [`Html`] from formatted HTML.
+///
+/// Unlike [`fhtml::format!`], nothing is rendered until the value is
+/// displayed, and unlike [`fhtml::format_args!`], the value can be stored and
+/// returned from functions, since the embedded expressions are moved into a
+/// closure. The markup is written straight into the formatter it is
+/// displayed in, e.g. when embedded in another template.
+///
+/// The closure can be rendered more than once, so values it iterates over
+/// should be borrowed, e.g. `@for item in &items`.
+///
+/// ```
+/// fn list(items: Vec<&str>) -> impl fhtml::Render + '_ {
+///     fhtml::html! {
+///         <ul>@for item in &items { <li>{item}</li> }</ul>
+///     }
+/// }
+///
+/// assert_eq!(
+///     fhtml::format!(<nav>{list(vec!["a", "<b>"])}</nav>),
+///     "<nav><ul><li>a</li><li>&lt;b&gt;</li></ul></nav>"
+/// );
+/// ```
+///
+/// [`fhtml::format!`]: crate::format
+/// [`fhtml::format_args!`]: crate::format_args
+#[macro_export]
+macro_rules! html {
+    ($($arg:tt)*) => {
+        $crate::Html::new(move |f: &mut ::core::fmt::Formatter<'_>| {
+            f.write_fmt($crate::format_args!($($arg)*))
+        })
+    };
+}
+
 /// Escapes special HTML characters in a string.
+///
+/// This allocates a new `String`, see [`escape_into`] and [`Escaped`] for
+/// escaping without allocating. Requires the `alloc` feature.
+#[cfg(feature = "alloc")]
 #[inline]
-pub fn escape<T: AsRef<str>>(input: T) -> String {
+pub fn escape<T: AsRef<str>>(input: T) -> alloc::string::String {
     let input = input.as_ref();
-    let mut escaped = String::with_capacity(input.len());
-    for c in input.chars() {
-        match c {
-            '&' => escaped.push_str("&amp;"),
-            '<' => escaped.push_str("&lt;"),
-            '>' => escaped.push_str("&gt;"),
-            '"' => escaped.push_str("&quot;"),
-            '\'' => escaped.push_str("&#39;"),
-            _ => escaped.push(c),
-        }
-    }
+    let mut escaped = alloc::string::String::with_capacity(input.len());
+    let _ = escape_into(&mut escaped, input);
     escaped
 }
 
@@ -119,4 +876,747 @@ mod tests {
             "<img src=\"/foo.png\" alt=\"foo\">"
         );
     }
+
+    #[test]
+    fn bare_attrs() {
+        assert_eq!(
+            crate::format!(<details open><summary>"a"</summary></details>),
+            "<details open><summary>a</summary></details>"
+        );
+        assert_eq!(
+            crate::concat!(<input type="checkbox" checked disabled={false} />),
+            "<input type=\"checkbox\" checked>"
+        );
+    }
+
+    #[test]
+    fn bool_attrs() {
+        let input = |checked: bool| crate::format!(<input type="checkbox" checked={checked} />);
+        assert_eq!(input(true), "<input type=\"checkbox\" checked>");
+        assert_eq!(input(false), "<input type=\"checkbox\">");
+        assert_eq!(
+            crate::format!(<div aria-hidden={true} hidden></div>),
+            "<div aria-hidden=\"true\" hidden></div>"
+        );
+        let div = |hidden: bool| crate::format!(<div hidden={hidden}></div>);
+        assert_eq!(div(true), "<div hidden></div>");
+        assert_eq!(div(false), "<div></div>");
+        assert_eq!(
+            crate::format!(<div hidden="until-found"></div>),
+            "<div hidden=\"until-found\"></div>"
+        );
+    }
+
+    #[test]
+    fn optional_attrs() {
+        let link = |title: Option<&str>, href: &Option<String>| crate::format!(<a title?={title} href?={href} lang?="en"></a>);
+        assert_eq!(link(None, &None), "<a lang=\"en\"></a>");
+        assert_eq!(
+            link(Some("\"x\""), &Some("javascript:x".to_owned())),
+            "<a title=\"&quot;x&quot;\" href=\"about:invalid\" \
+             lang=\"en\"></a>"
+        );
+    }
+
+    #[test]
+    fn class_lists() {
+        let button = |is_primary: bool, extra: Option<&str>| {
+            crate::format! {
+                <button class=["btn", "btn-primary" => is_primary, {extra}]>
+                </button>
+            }
+        };
+        assert_eq!(button(false, None), "<button class=\"btn\"></button>");
+        assert_eq!(
+            button(true, Some("  wide \"x\" btn ")),
+            "<button class=\"btn btn-primary wide &quot;x&quot;\"></button>"
+        );
+
+        let empty = "";
+        assert_eq!(
+            crate::format!(<p class=["" => true, {empty}]></p>),
+            "<p></p>"
+        );
+        assert_eq!(
+            crate::concat!(<p class=["a b", "b", "c"]></p>),
+            "<p class=\"a b c\"></p>"
+        );
+    }
+
+    // Tags with both attributes and spreads are warned about.
+    #[allow(deprecated)]
+    #[test]
+    fn spread_attrs() {
+        let attrs = vec![
+            ("hx-get", "/items?a=1&b=2"),
+            ("data-name", "\"Tom\""),
+            ("ID", "other"),
+            ("hx-get", "/other"),
+            ("onclick", "alert(1)"),
+            ("x\" y", "z"),
+            ("href", "javascript:alert(1)"),
+        ];
+        assert_eq!(
+            crate::format!(<div id="main" ..{attrs}></div>),
+            "<div id=\"main\" hx-get=\"/items?a=1&amp;b=2\" \
+             data-name=\"&quot;Tom&quot;\" href=\"about:invalid\"></div>"
+        );
+
+        let map = std::collections::BTreeMap::from([
+            ("a".to_owned(), 1),
+            ("b".to_owned(), 2),
+        ]);
+        assert_eq!(
+            crate::format!(<br ..{map} ..{[("c", 'c')]} />),
+            "<br a=\"1\" b=\"2\" c=\"c\">"
+        );
+
+        let names = ["x", "y"];
+        assert_eq!(
+            crate::format!(<br ..{names.iter().map(|n| (n, n.len()))} />),
+            "<br x=\"1\" y=\"1\">"
+        );
+        assert_eq!(
+            crate::format!(<p ..{&names.map(|n| (n, n))}>@for _ in 0..2 { <br ..{&[("z", 1)]} /> }</p>),
+            "<p x=\"x\" y=\"y\"><br z=\"1\"><br z=\"1\"></p>"
+        );
+    }
+
+    #[test]
+    fn uppercase_elements() {
+        assert_eq!(
+            crate::format!(<DIV>"x"</DIV><Svg viewBox="0 0 1 1"></Svg>),
+            "<DIV>x</DIV><Svg viewBox=\"0 0 1 1\"></Svg>"
+        );
+    }
+
+    #[allow(deprecated)]
+    #[test]
+    fn spread_attrs_precedence() {
+        let first = vec![("Title", "first"), ("title", "second")];
+        let second = std::collections::BTreeMap::from([
+            ("ID", "spread"),
+            ("TITLE", "third"),
+            ("lang", "en"),
+        ]);
+        // The tag's own attributes win over the pairs, and earlier pairs win
+        // over later ones, also across spreads.
+        assert_eq!(
+            crate::format!(<p id="tag" ..{first} ..{second}></p>),
+            "<p id=\"tag\" Title=\"first\" lang=\"en\"></p>"
+        );
+    }
+
+    #[test]
+    fn comments() {
+        assert_eq!(
+            crate::format!(<!-- " start " --> <p>"a"</p> <!--"end"-->),
+            "<!-- start --><p>a</p><!--end-->"
+        );
+        assert_eq!(
+            crate::concat!(<!--"[if IE]><p>IE</p><![endif]"-->),
+            "<!--[if IE]><p>IE</p><![endif]-->"
+        );
+
+        let items = [1, 2];
+        assert_eq!(
+            crate::format! {
+                #![fhtml(minify)]
+                <!-- "list" -->
+                <ul>
+                    @for item in items {
+                        <!-- "item" -->
+                        <li>{item}</li>
+                    }
+                </ul>
+            },
+            "<ul><li>1</li><li>2</li></ul>"
+        );
+        assert_eq!(
+            crate::concat!(#![fhtml(minify)] <!-- "a" --> <p>"b"</p>),
+            "<p>b</p>"
+        );
+    }
+
+    #[test]
+    fn components() {
+        use std::fmt;
+
+        #[derive(Default)]
+        struct Button<'a> {
+            label: &'a str,
+            primary: bool,
+            children: crate::Slot<'a>,
+        }
+
+        impl crate::Component for Button<'_> {
+            fn render(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                crate::write! { f,
+                    <button class=["btn", "btn-primary" => self.primary]>
+                        {self.label}
+                        {self.children}
+                    </button>
+                }
+            }
+        }
+
+        struct Panel<'a> {
+            children: crate::Slot<'a>,
+        }
+
+        impl crate::Component for Panel<'_> {
+            fn render(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                crate::write!(f, <div class="panel">{self.children}</div>)
+            }
+        }
+
+        let items = ["a", "b"];
+        assert_eq!(
+            crate::format! {
+                <Panel>
+                    <Panel>
+                        @for item in items {
+                            <Button label={item} primary ..{Default::default()} />
+                        }
+                    </Panel>
+                    <Button label="<c>" primary={false}><b>"!"</b></Button>
+                </Panel>
+            },
+            "<div class=\"panel\"><div class=\"panel\">\
+             <button class=\"btn btn-primary\">a</button>\
+             <button class=\"btn btn-primary\">b</button></div>\
+             <button class=\"btn\">&lt;c&gt;<b>!</b></button></div>"
+        );
+    }
+
+    #[test]
+    fn slots() {
+        use std::fmt;
+
+        struct Dialog<'a> {
+            title: crate::Slot<'a>,
+            children: crate::Slot<'a>,
+        }
+
+        impl crate::Component for Dialog<'_> {
+            fn render(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                crate::write! { f,
+                    <dialog><h2>{self.title}</h2>{self.children}</dialog>
+                }
+            }
+        }
+
+        mod helpers {
+            pub fn wrap(
+                tag: &str,
+                slots: crate::Slots<'_>,
+            ) -> crate::PreEscaped<String> {
+                crate::PreEscaped(std::format!(
+                    "<{tag}>{}{}</{tag}>",
+                    slots,
+                    slots.get("footer").unwrap_or_default()
+                ))
+            }
+        }
+
+        fn greet(name: &str) -> String {
+            std::format!("Hello, {}", name)
+        }
+
+        let items = ["a", "<b>"];
+        assert_eq!(
+            crate::format! {
+                <Dialog>
+                    <slot:title>"Items"</slot:title>
+                    @helpers::wrap("ul") {
+                        @for item in items {
+                            <li>@greet(item)</li>
+                        }
+                        <slot:footer><li>"end"</li></slot:footer>
+                    }
+                </Dialog>
+            },
+            "<dialog><h2>Items</h2><ul><li>Hello, a</li>\
+             <li>Hello, &lt;b&gt;</li><li>end</li></ul></dialog>"
+        );
+    }
+
+    #[test]
+    fn slots_in_attrs() {
+        use std::fmt;
+
+        struct Link<'a> {
+            children: crate::Slot<'a>,
+        }
+
+        impl crate::Component for Link<'_> {
+            fn render(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                crate::write! { f,
+                    <a title={self.children} href={self.children}>
+                        {self.children}
+                    </a>
+                }
+            }
+        }
+
+        let name = "\" onmouseover=\"alert(1)";
+        let url = "javascript:alert(1)";
+        assert_eq!(
+            crate::format!(<Link><b>{name}</b></Link><Link>{url}</Link>),
+            "<a title=\"<b>&quot; onmouseover=&quot;alert(1)</b>\" \
+             href=\"<b>&quot; onmouseover=&quot;alert(1)</b>\">\
+             <b>\" onmouseover=\"alert(1)</b></a>\
+             <a title=\"javascript:alert(1)\" href=\"about:invalid\">\
+             javascript:alert(1)</a>"
+        );
+    }
+
+    #[test]
+    fn derive_render() {
+        #[derive(crate::Render)]
+        #[template(
+            <li id={self.id}>
+                @if let Some(label) = label { {label} } @else { "-" }
+            </li>
+        )]
+        struct Item<'a> {
+            id: u32,
+            label: Option<&'a str>,
+        }
+
+        #[derive(crate::Render)]
+        #[template(<p>{self.0}</p>)]
+        struct Text<T: crate::Render>(T);
+
+        #[derive(crate::Render)]
+        #[template(
+            @match self {
+                Self::Empty => { <p>"Empty"</p> }
+                Self::Items(items) => {
+                    <ul>@for item in items { {item} }</ul>
+                }
+            }
+        )]
+        enum List<'a> {
+            Empty,
+            Items(Vec<Item<'a>>),
+        }
+
+        let items = vec![
+            Item {
+                id: 1,
+                label: Some("<a>"),
+            },
+            Item { id: 2, label: None },
+        ];
+        assert_eq!(
+            List::Items(items).to_string(),
+            "<ul><li id=\"1\">&lt;a&gt;</li><li id=\"2\">-</li></ul>"
+        );
+        assert_eq!(List::Empty.to_string(), "<p>Empty</p>");
+        assert_eq!(
+            crate::format!(<div>{Text("<b>")}</div>),
+            "<div><p>&lt;b&gt;</p></div>"
+        );
+
+        // In attributes, the output is escaped like any other value.
+        #[derive(crate::Render)]
+        #[template({self.0})]
+        struct Link<'a>(&'a str);
+
+        let text = Text("\" onmouseover=\"alert(1)");
+        assert_eq!(
+            crate::format!(<a title={text} href={Link("javascript:x")}></a>),
+            "<a title=\"<p>&quot; onmouseover=&quot;alert(1)</p>\" \
+             href=\"about:invalid\"></a>"
+        );
+    }
+
+    #[test]
+    fn include() {
+        let items = ["a", "<b>"];
+        assert_eq!(
+            std::fmt::format(crate::include!(
+                "testdata/page.fhtml",
+                title = "<Items>",
+                items = &items,
+            )),
+            "<!DOCTYPE html><html><head><title>&lt;Items&gt;</title></head>\
+             <body><!-- items --><ul><li>a</li><li>&lt;b&gt;</li></ul></body>\
+             </html>"
+        );
+    }
+
+    #[test]
+    fn layouts() {
+        let title = "<Home>";
+        assert_eq!(
+            crate::format! {
+                @extends "testdata/layouts/base.fhtml"
+                @block title { {title} }
+                @block content { <p>"Welcome"</p> }
+            },
+            "<!DOCTYPE html><html><head><title>&lt;Home&gt;</title></head>\
+             <body><p>Welcome</p></body></html>"
+        );
+
+        assert_eq!(
+            crate::format! {
+                @extends "testdata/layouts/docs.fhtml"
+                @block article { <h1>"Layouts"</h1> }
+            },
+            "<!DOCTYPE html><html><head><title>Untitled</title></head>\
+             <body><nav>Docs</nav><article><h1>Layouts</h1></article></body>\
+             </html>"
+        );
+    }
+
+    #[test]
+    fn html() {
+        fn item(name: String, done: bool) -> impl crate::Render {
+            crate::html!(<li class=["done" => done]>{name}</li>)
+        }
+
+        let items = [item("<a>".to_owned(), true), item("b".to_owned(), false)];
+        let list = crate::html!(<ul>@for item in &items { {item} }</ul>);
+        assert_eq!(
+            list.to_string(),
+            "<ul><li class=\"done\">&lt;a&gt;</li><li>b</li></ul>"
+        );
+        assert_eq!(
+            crate::format!(<div>{list}</div>),
+            std::format!("<div>{}</div>", list)
+        );
+
+        let name = "\" onmouseover=\"alert(1)";
+        let title = crate::html!({ name });
+        assert_eq!(
+            crate::format!(<a title={title}>"x"</a>),
+            "<a title=\"&quot; onmouseover=&quot;alert(1)\">x</a>"
+        );
+        let url = "javascript:alert(1)";
+        assert_eq!(
+            crate::format!(<a href={crate::html!({url})}>"x"</a>),
+            "<a href=\"about:invalid\">x</a>"
+        );
+    }
+
+    #[test]
+    fn write_io() {
+        fn list(
+            w: &mut impl std::io::Write,
+            items: &[&str],
+        ) -> std::io::Result<()> {
+            crate::write_io!(w, <ul>@for item in items { <li>{item}</li> }</ul>)
+        }
+
+        let mut buf = Vec::new();
+        let name = "<Bob>";
+        crate::write_io!(buf, <p title={name}>"Hi "{name}</p>).unwrap();
+        list(&mut buf, &["a", "<b>"]).unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "<p title=\"<Bob>\">Hi &lt;Bob&gt;</p>\
+             <ul><li>a</li><li>&lt;b&gt;</li></ul>"
+        );
+
+        let mut full = [0; 4];
+        assert!(crate::write_io!(&mut full[..], <p>"Hello"</p>).is_err());
+
+        // `?` in an expression applies to the enclosing function.
+        fn count(
+            w: &mut Vec<u8>,
+            n: &str,
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            crate::write_io!(w, <p>{n.parse::<u32>()?}</p>)?;
+            Ok(())
+        }
+
+        let mut buf = Vec::new();
+        count(&mut buf, "3").unwrap();
+        assert!(count(&mut buf, "x").is_err());
+        assert_eq!(buf, b"<p>3</p><p>");
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn write_async() {
+        use std::io;
+        use std::pin::Pin;
+        use std::task::{Context, Poll};
+
+        /// Records the output written between flushes.
+        #[derive(Default)]
+        struct Chunks {
+            buf: Vec<u8>,
+            chunks: Vec<String>,
+        }
+
+        impl tokio::io::AsyncWrite for Chunks {
+            fn poll_write(
+                mut self: Pin<&mut Self>,
+                _: &mut Context<'_>,
+                buf: &[u8],
+            ) -> Poll<io::Result<usize>> {
+                self.buf.extend_from_slice(buf);
+                Poll::Ready(Ok(buf.len()))
+            }
+
+            fn poll_flush(
+                mut self: Pin<&mut Self>,
+                _: &mut Context<'_>,
+            ) -> Poll<io::Result<()>> {
+                let chunk = String::from_utf8(std::mem::take(&mut self.buf))
+                    .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+                self.chunks.push(chunk);
+                Poll::Ready(Ok(()))
+            }
+
+            fn poll_shutdown(
+                self: Pin<&mut Self>,
+                _: &mut Context<'_>,
+            ) -> Poll<io::Result<()>> {
+                Poll::Ready(Ok(()))
+            }
+        }
+
+        async fn page(w: &mut Chunks, items: &[&str]) -> io::Result<()> {
+            crate::write_async!(w,
+                <!DOCTYPE html>
+                <html><body>
+                @let title = "<Items>";
+                <h1>{title}</h1>
+                <ul>@for item in items { <li>{item}</li> }</ul>
+                </body></html>
+            )
+            .await
+        }
+
+        let mut out = Chunks::default();
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(page(&mut out, &["a", "<b>"]))
+            .unwrap();
+        assert_eq!(
+            out.chunks,
+            [
+                "<!DOCTYPE html><html><body><h1>&lt;Items&gt;</h1>",
+                "<ul><li>a</li>",
+                "<li>&lt;b&gt;</li>",
+                "</ul></body></html>",
+            ]
+        );
+    }
+
+    #[test]
+    fn size_hint() {
+        struct Name;
+
+        impl crate::Render for Name {
+            fn render(
+                &self,
+                f: &mut std::fmt::Formatter<'_>,
+                ctx: crate::Context,
+            ) -> std::fmt::Result {
+                ctx.write_str(f, "Bob")
+            }
+
+            fn size_hint(&self) -> usize {
+                3
+            }
+        }
+
+        let title = Some("x");
+        let output = crate::format! {
+            <p class=["a", "b" => true] title?={title}>"Hello, "{Name}</p>
+        };
+        assert_eq!(output, "<p class=\"a b\" title=\"x\">Hello, Bob</p>");
+        assert_eq!(output.capacity(), output.len());
+    }
+
+    #[test]
+    fn escape_exprs() {
+        let text = "<b>\"Tom\" & 'Jerry'</b>";
+        assert_eq!(
+            crate::format!(<p title={text}>{text}</p>),
+            "<p title=\"<b>&quot;Tom&quot; &amp; &#39;Jerry&#39;</b>\">\
+             &lt;b&gt;\"Tom\" &amp; 'Jerry'&lt;/b&gt;</p>"
+        );
+    }
+
+    #[test]
+    fn escape_urls() {
+        let safe = "/path?a=1&b=\"2\"";
+        let unsafe_ = " JavaScript:alert(1)";
+        assert_eq!(
+            crate::format!(<a href={safe}>{unsafe_}</a>),
+            "<a href=\"/path?a=1&amp;b=&quot;2&quot;\"> \
+             JavaScript:alert(1)</a>"
+        );
+        assert_eq!(
+            crate::format!(<img src={unsafe_} alt="" />),
+            "<img src=\"about:invalid\" alt=\"\">"
+        );
+        assert_eq!(
+            crate::format!(<a href={format_args!("java{}", "script:")}></a>),
+            "<a href=\"about:invalid\"></a>"
+        );
+        let srcset = std::format!("a.png 1x,{} 2x", unsafe_);
+        assert_eq!(
+            crate::format!(<img srcset={srcset} alt="" />),
+            "<img srcset=\"a.png 1x, about:invalid 2x\" alt=\"\">"
+        );
+        let doc = "<script>alert(1)</script>";
+        assert_eq!(
+            crate::format!(<iframe srcdoc={doc}></iframe>),
+            "<iframe srcdoc=\"&amp;lt;script&amp;gt;alert(1)&amp;lt;/script&amp;gt;\"></iframe>"
+        );
+        let attrs = [("srcdoc", doc)];
+        assert_eq!(
+            crate::format!(<iframe ..{attrs}></iframe>),
+            "<iframe srcdoc=\"&amp;lt;script&amp;gt;alert(1)&amp;lt;/script&amp;gt;\"></iframe>"
+        );
+    }
+
+    #[test]
+    fn pre_escaped() {
+        let inner = crate::format!(<b>"bold"</b>);
+        assert_eq!(
+            crate::format!(<p>{crate::PreEscaped(&inner)}{crate::raw("<i>")}</p>),
+            "<p><b>bold</b><i></p>"
+        );
+        assert_eq!(
+            crate::format!(<p>{crate::format_args!(<br />)}</p>),
+            "<p>&lt;br&gt;</p>"
+        );
+    }
+
+    #[test]
+    fn render_non_text() {
+        let none: Option<&str> = None;
+        assert_eq!(
+            crate::format!(<p>{1} {2.5} {true} {'<'} {Some("&")} {none}</p>),
+            "<p>12.5true&lt;&amp;</p>"
+        );
+    }
+
+    #[test]
+    fn control_flow_if() {
+        let list = |n: i32| {
+            crate::format! {
+                <p>
+                    @if n == 0 {
+                        "none"
+                    } @else if let 1 = n {
+                        "one"
+                    } @else {
+                        {n}
+                    }
+                    @if n < 0 { "!" }
+                </p>
+            }
+        };
+        assert_eq!(list(0), "<p>none</p>");
+        assert_eq!(list(1), "<p>one</p>");
+        assert_eq!(list(-2), "<p>-2!</p>");
+    }
+
+    #[test]
+    fn control_flow_for() {
+        let items = ["<a>", "b"];
+        assert_eq!(
+            crate::format! {
+                <ul>
+                    @for (i, f) in items.iter().enumerate() {
+                        <li id={i}>{f}</li>
+                    }
+                </ul>
+            },
+            "<ul><li id=\"0\">&lt;a&gt;</li><li id=\"1\">b</li></ul>"
+        );
+    }
+
+    #[test]
+    fn control_flow_for_owned() {
+        let items = || vec!["<a>".to_owned(), "b".to_owned()];
+        let expected = "<ul><li>&lt;a&gt;</li><li>b</li></ul>";
+        let owned = items();
+        assert_eq!(
+            crate::format!(<ul>@for item in owned { <li>{item}</li> }</ul>),
+            expected
+        );
+
+        let owned = items();
+        let mut bytes = Vec::new();
+        crate::write_io!(bytes, <ul>@for item in owned { <li>{item}</li> }</ul>)
+            .unwrap();
+        assert_eq!(bytes, expected.as_bytes());
+
+        // The arguments of `write!` may be displayed more than once, so they
+        // borrow the values.
+        let owned = items();
+        let mut buf = String::new();
+        crate::write!(buf, <ul>@for item in &owned { <li>{item}</li> }</ul>)
+            .unwrap();
+        assert_eq!(buf, expected);
+    }
+
+    #[test]
+    fn format_args_twice() {
+        let twice = |args: std::fmt::Arguments<'_>| {
+            [args.to_string(), args.to_string()]
+        };
+        let items = ["<a>", "b"];
+        let attrs = [("x", 1)];
+        let expected = "<ul x=\"1\"><li>&lt;a&gt;</li><li>b</li></ul>2";
+        assert_eq!(
+            twice(crate::format_args!(
+                @let n = items.len();
+                <ul ..{attrs}>@for item in items { <li>{item}</li> }</ul>{n}
+            )),
+            [expected, expected]
+        );
+    }
+
+    #[test]
+    fn control_flow_match() {
+        let render = |value: Option<i32>| {
+            crate::format! {
+                @match value {
+                    Some(x) if x > 9 => { <b>"big"</b> }
+                    Some(x) => { <i>{x}</i> },
+                    None => {}
+                }
+            }
+        };
+        assert_eq!(render(Some(10)), "<b>big</b>");
+        assert_eq!(render(Some(1)), "<i>1</i>");
+        assert_eq!(render(None), "");
+    }
+
+    #[test]
+    fn control_flow_let() {
+        assert_eq!(
+            crate::format! {
+                <div>
+                    @let x = 1 + 1;
+                    <p>{x}</p>
+                    @for y in 0..x {
+                        @let z = y * 10;
+                        {z}
+                    }
+                </div>
+            },
+            "<div><p>2</p>010</div>"
+        );
+    }
+
+    #[test]
+    fn render_with_specs() {
+        assert_eq!(
+            crate::format!(<p>{"<a>":?} {1.2345:.2}</p>),
+            "<p>\"&lt;a&gt;\"1.23</p>"
+        );
+    }
 }
@@ -0,0 +1,338 @@
+#[cfg(feature = "alloc")]
+use alloc::borrow::Cow;
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+#[cfg(feature = "alloc")]
+use alloc::rc::Rc;
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+use alloc::sync::Arc;
+use core::fmt;
+
+use crate::escape::{Context, Escaped};
+
+/// A value that can be rendered as HTML.
+///
+/// Every `{expr}` in an fhtml macro is rendered through this trait, and
+/// implementations for text-like types escape special HTML characters, so
+/// user data can be interpolated without calling [`escape`] by hand.
+///
+/// Text should be escaped according to the [`Context`] the value is rendered
+/// in, e.g. using [`Context::write_str`].
+///
+/// ```
+/// use std::fmt;
+///
+/// struct User {
+///     name: String,
+/// }
+///
+/// impl fhtml::Render for User {
+///     fn render(
+///         &self,
+///         f: &mut fmt::Formatter<'_>,
+///         ctx: fhtml::Context,
+///     ) -> fmt::Result {
+///         ctx.write_args(f, format_args!("@{}", self.name))
+///     }
+/// }
+///
+/// let user = User { name: "<bob>".to_owned() };
+/// assert_eq!(
+///     fhtml::format!(<p title={&user}>{&user}</p>),
+///     "<p title=\"@<bob>\">@&lt;bob&gt;</p>"
+/// );
+/// ```
+///
+/// [`escape`]: crate::escape
+pub trait Render {
+    /// Renders the value into the given formatter, escaped for the given
+    /// context.
+    fn render(&self, f: &mut fmt::Formatter<'_>, ctx: Context) -> fmt::Result;
+
+    /// Returns an estimate of the length of the rendered value in bytes.
+    ///
+    /// [`fhtml::format!`] uses the hints of the embedded values, together
+    /// with the length of the static markup, as the initial capacity of the
+    /// `String`. The hint is only an optimization, so it may be inaccurate,
+    /// and defaults to `0`.
+    ///
+    /// [`fhtml::format!`]: crate::format
+    #[inline]
+    fn size_hint(&self) -> usize {
+        0
+    }
+}
+
+/// A wrapper for trusted markup that is rendered without escaping.
+///
+/// Values wrapped in `PreEscaped` are inserted verbatim in every [`Context`],
+/// including URL attributes.
+///
+/// This is useful for embedding the output of one fhtml macro in another
+/// without escaping it twice.
+///
+/// ```
+/// let item = fhtml::format!(<li>"Item"</li>);
+/// assert_eq!(
+///     fhtml::format!(<ul>{fhtml::PreEscaped(item)}</ul>),
+///     "<ul><li>Item</li></ul>"
+/// );
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PreEscaped<T>(pub T);
+
+/// Marks a value as trusted markup, see [`PreEscaped`].
+#[inline]
+pub fn raw<T: fmt::Display>(value: T) -> PreEscaped<T> {
+    PreEscaped(value)
+}
+
+impl<T: fmt::Display> fmt::Display for PreEscaped<T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<T: fmt::Display> Render for PreEscaped<T> {
+    #[inline]
+    fn render(&self, f: &mut fmt::Formatter<'_>, _: Context) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Lazily rendered markup, created by [`fhtml::html!`].
+///
+/// `Html` wraps a closure that writes the markup into a formatter when the
+/// value is displayed, so it can be stored or returned from a function
+/// without rendering it into an intermediate `String`. The output is trusted
+/// markup, so it is not escaped when embedded as text in another template.
+/// In an attribute value, it is escaped like any other value.
+///
+/// ```
+/// use fhtml::Render;
+///
+/// fn greeting(name: String) -> impl Render {
+///     fhtml::html!(<p>"Hello, "{name}</p>)
+/// }
+///
+/// assert_eq!(
+///     fhtml::format!(<div>{greeting("<Bob>".to_owned())}</div>),
+///     "<div><p>Hello, &lt;Bob&gt;</p></div>"
+/// );
+/// ```
+///
+/// [`fhtml::html!`]: crate::html
+#[derive(Clone, Copy)]
+pub struct Html<F>(F);
+
+impl<F> Html<F>
+where
+    F: Fn(&mut fmt::Formatter<'_>) -> fmt::Result,
+{
+    /// Creates markup that renders by calling `f`.
+    #[inline]
+    pub fn new(f: F) -> Self {
+        Self(f)
+    }
+}
+
+impl<F> fmt::Debug for Html<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Html").finish_non_exhaustive()
+    }
+}
+
+impl<F> fmt::Display for Html<F>
+where
+    F: Fn(&mut fmt::Formatter<'_>) -> fmt::Result,
+{
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (self.0)(f)
+    }
+}
+
+impl<F> Render for Html<F>
+where
+    F: Fn(&mut fmt::Formatter<'_>) -> fmt::Result,
+{
+    #[inline]
+    fn render(&self, f: &mut fmt::Formatter<'_>, ctx: Context) -> fmt::Result {
+        crate::__private::render_markup(self, f, ctx)
+    }
+}
+
+impl<T: fmt::Display> Render for Escaped<T> {
+    #[inline]
+    fn render(&self, f: &mut fmt::Formatter<'_>, ctx: Context) -> fmt::Result {
+        ctx.write_args(f, format_args!("{}", self.0))
+    }
+}
+
+impl Render for str {
+    #[inline]
+    fn render(&self, f: &mut fmt::Formatter<'_>, ctx: Context) -> fmt::Result {
+        ctx.write_str(f, self)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> usize {
+        self.len()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Render for String {
+    #[inline]
+    fn render(&self, f: &mut fmt::Formatter<'_>, ctx: Context) -> fmt::Result {
+        self.as_str().render(f, ctx)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> usize {
+        self.len()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Render for Cow<'_, str> {
+    #[inline]
+    fn render(&self, f: &mut fmt::Formatter<'_>, ctx: Context) -> fmt::Result {
+        self.as_ref().render(f, ctx)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> usize {
+        self.len()
+    }
+}
+
+impl Render for char {
+    #[inline]
+    fn render(&self, f: &mut fmt::Formatter<'_>, ctx: Context) -> fmt::Result {
+        self.encode_utf8(&mut [0; 4]).render(f, ctx)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> usize {
+        self.len_utf8()
+    }
+}
+
+impl Render for fmt::Arguments<'_> {
+    #[inline]
+    fn render(&self, f: &mut fmt::Formatter<'_>, ctx: Context) -> fmt::Result {
+        ctx.write_args(f, *self)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> usize {
+        self.as_str().map_or(0, str::len)
+    }
+}
+
+impl<T: Render> Render for Option<T> {
+    #[inline]
+    fn render(&self, f: &mut fmt::Formatter<'_>, ctx: Context) -> fmt::Result {
+        match self {
+            Some(value) => value.render(f, ctx),
+            None => Ok(()),
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> usize {
+        self.as_ref().map_or(0, T::size_hint)
+    }
+}
+
+impl<T: Render + ?Sized> Render for &T {
+    #[inline]
+    fn render(&self, f: &mut fmt::Formatter<'_>, ctx: Context) -> fmt::Result {
+        (**self).render(f, ctx)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> usize {
+        (**self).size_hint()
+    }
+}
+
+impl<T: Render + ?Sized> Render for &mut T {
+    #[inline]
+    fn render(&self, f: &mut fmt::Formatter<'_>, ctx: Context) -> fmt::Result {
+        (**self).render(f, ctx)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> usize {
+        (**self).size_hint()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: Render + ?Sized> Render for Box<T> {
+    #[inline]
+    fn render(&self, f: &mut fmt::Formatter<'_>, ctx: Context) -> fmt::Result {
+        (**self).render(f, ctx)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> usize {
+        (**self).size_hint()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: Render + ?Sized> Render for Rc<T> {
+    #[inline]
+    fn render(&self, f: &mut fmt::Formatter<'_>, ctx: Context) -> fmt::Result {
+        (**self).render(f, ctx)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> usize {
+        (**self).size_hint()
+    }
+}
+
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+impl<T: Render + ?Sized> Render for Arc<T> {
+    #[inline]
+    fn render(&self, f: &mut fmt::Formatter<'_>, ctx: Context) -> fmt::Result {
+        (**self).render(f, ctx)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> usize {
+        (**self).size_hint()
+    }
+}
+
+// Types whose `Display` output never contains special HTML characters.
+macro_rules! impl_render_for_display {
+    ($($ty:ty)*) => {
+        $(
+            impl Render for $ty {
+                #[inline]
+                fn render(
+                    &self,
+                    f: &mut fmt::Formatter<'_>,
+                    _: Context,
+                ) -> fmt::Result {
+                    fmt::Display::fmt(self, f)
+                }
+            }
+        )*
+    };
+}
+
+impl_render_for_display! {
+    bool
+    i8 i16 i32 i64 i128 isize
+    u8 u16 u32 u64 u128 usize
+    f32 f64
+}